@@ -0,0 +1,230 @@
+//! Up-front estimates of how long a program will take to plot, before any paper is committed.
+
+use std::time::Duration;
+
+use crate::simulator::{Move, PlotterState};
+use crate::{hp7470a, Coordinate, HpglCommand, HpglProgram, PlotterWriteable};
+
+/// Floors under the carriage's rates, in cm/s and cm/s², so that a `VS0` can't stall it forever.
+const MIN_VELOCITY: f32 = 0.1;
+const MIN_ACCELERATION: f32 = 1.;
+
+/// Physical characteristics of a plotter and the link it's driven over.
+#[derive(Clone, Debug)]
+pub struct PlotterModel {
+    /// Top carriage speed, in cm/s. Pen-down moves are further limited by `VS`.
+    pub max_velocity: f32,
+    /// Carriage acceleration, in cm/s². Every move is assumed to start and end at rest.
+    pub acceleration: f32,
+    pub pen_lift: Duration,
+    pub pen_lower: Duration,
+    /// Time to put away one pen and fetch another.
+    pub pen_change: Duration,
+    pub baud_rate: u32,
+    /// Bits on the wire per character, counting start, parity and stop bits.
+    pub bits_per_char: u32,
+}
+
+impl PlotterModel {
+    /// Rough figures for an HP 7470A on an 8N1 link.
+    pub fn hp7470a(baud_rate: u32) -> Self {
+        Self {
+            max_velocity: hp7470a::MAX_VELOCITY,
+            acceleration: 2. * 981.,
+            pen_lift: Duration::from_millis(50),
+            pen_lower: Duration::from_millis(50),
+            pen_change: Duration::from_secs(2),
+            baud_rate,
+            bits_per_char: 10,
+        }
+    }
+
+    /// Time the carriage spends on `m`, with a trapezoidal velocity profile. `VS` only governs
+    /// pen-down moves; the pen always travels at full speed when raised.
+    pub fn move_duration(&self, m: &Move) -> Duration {
+        let velocity = match (m.pen_down, m.velocity) {
+            (true, Some(velocity)) => velocity.min(self.max_velocity),
+            _ => self.max_velocity,
+        }
+        .max(MIN_VELOCITY);
+        let acceleration = self.acceleration.max(MIN_ACCELERATION);
+        let distance = m.length() / Coordinate::UNITS_PER_CM;
+
+        let secs = if distance >= velocity * velocity / acceleration {
+            // accelerate to full speed, cruise, decelerate
+            distance / velocity + velocity / acceleration
+        } else {
+            // never reaches full speed
+            2. * (distance / acceleration).sqrt()
+        };
+
+        Duration::from_secs_f32(secs)
+    }
+
+    /// Mechanical time to execute `command` from `state`, which is stepped past it.
+    pub fn command_duration(&self, state: &mut PlotterState, command: &HpglCommand) -> Duration {
+        let overhead = match command {
+            HpglCommand::PenUp if state.pen_down => self.pen_lift,
+            HpglCommand::PenDown if !state.pen_down => self.pen_lower,
+            HpglCommand::SelectPen { pen } if *pen != state.pen => self.pen_change,
+            _ => Duration::default(),
+        };
+
+        state
+            .apply(command)
+            .iter()
+            .map(|m| self.move_duration(m))
+            .fold(overhead, |total, d| total + d)
+    }
+
+    /// Time to push `bytes` characters down the serial link, taking a baud rate of 0 as 1.
+    pub fn transmit_duration(&self, bytes: usize) -> Duration {
+        Duration::from_secs_f64(
+            (bytes as u64 * self.bits_per_char as u64) as f64 / self.baud_rate.max(1) as f64,
+        )
+    }
+
+    /// Models the plotter executing `program` as it arrives over the serial link: a command can't
+    /// start before it has been fully received, nor before the previous one has finished.
+    pub fn estimate(&self, program: &HpglProgram) -> Duration {
+        let mut state = PlotterState::default();
+        let mut received = Duration::default();
+        let mut finished = Duration::default();
+        let mut buf: Vec<u8> = Vec::new();
+
+        for command in program.commands() {
+            buf.clear();
+            command
+                .write(&mut buf)
+                .expect("writing to a Vec can't fail");
+
+            received += self.transmit_duration(buf.len());
+            finished = finished.max(received) + self.command_duration(&mut state, command);
+        }
+
+        finished
+    }
+}
+
+impl Default for PlotterModel {
+    fn default() -> Self {
+        Self::hp7470a(9600)
+    }
+}
+
+/// Estimates plotting time for `program` on an HP 7470A at 9600 baud.
+pub fn estimate(program: &HpglProgram) -> Duration {
+    PlotterModel::default().estimate(program)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn model() -> PlotterModel {
+        PlotterModel {
+            max_velocity: 10.,
+            acceleration: 100.,
+            pen_lift: Duration::from_millis(100),
+            pen_lower: Duration::from_millis(100),
+            pen_change: Duration::from_secs(1),
+            baud_rate: 1_000_000,
+            bits_per_char: 10,
+        }
+    }
+
+    fn approx(a: Duration, b: Duration) -> bool {
+        (a.as_secs_f64() - b.as_secs_f64()).abs() < 1e-3
+    }
+
+    #[test]
+    fn long_and_short_moves() {
+        let model = model();
+        let mut state = PlotterState::default();
+
+        // 10cm: 0.1s up to speed, 0.9s cruising, 0.1s slowing down
+        let long = model.command_duration(
+            &mut state,
+            &HpglCommand::PlotAbsolute(Coordinate { x: 4000., y: 0. }.into()),
+        );
+        assert!(approx(long, Duration::from_secs_f32(1.1)));
+
+        // 0.25cm: never gets past 5cm/s
+        let short = model.command_duration(
+            &mut state,
+            &HpglCommand::PlotAbsolute(Coordinate { x: 4100., y: 0. }.into()),
+        );
+        assert!(approx(short, Duration::from_secs_f32(0.1)));
+    }
+
+    #[test]
+    fn pen_up_moves_ignore_velocity() {
+        let model = model();
+        let mut state = PlotterState::default();
+        model.command_duration(&mut state, &HpglCommand::VelocitySelect { velocity: 5. });
+
+        // 10cm travelling at full speed, then drawing back held to 5cm/s
+        let travel = model.command_duration(
+            &mut state,
+            &HpglCommand::PlotAbsolute(Coordinate { x: 4000., y: 0. }.into()),
+        );
+        assert!(approx(travel, Duration::from_secs_f32(1.1)));
+        state.pen_down = true;
+        let stroke = model.command_duration(
+            &mut state,
+            &HpglCommand::PlotAbsolute(Coordinate { x: 0., y: 0. }.into()),
+        );
+        assert!(approx(stroke, Duration::from_secs_f32(2.05)));
+    }
+
+    #[test]
+    fn stalled_rates_still_finish() {
+        let model = PlotterModel {
+            acceleration: 0.,
+            baud_rate: 0,
+            ..model()
+        };
+        let program: HpglProgram = vec![
+            HpglCommand::VelocitySelect { velocity: 0. },
+            HpglCommand::PenDown,
+            HpglCommand::PlotAbsolute(Coordinate { x: 400., y: 0. }.into()),
+        ]
+        .into();
+
+        assert!(model.estimate(&program) > Duration::from_secs(10));
+    }
+
+    #[test]
+    fn pen_overheads_only_on_change() {
+        let model = model();
+        let program: HpglProgram = vec![
+            HpglCommand::SelectPen { pen: 1 },
+            HpglCommand::SelectPen { pen: 1 },
+            HpglCommand::PenDown,
+            HpglCommand::PenDown,
+            HpglCommand::PenUp,
+            HpglCommand::PenUp,
+        ]
+        .into();
+
+        assert!(approx(
+            model.estimate(&program),
+            Duration::from_secs_f32(1.2)
+        ));
+    }
+
+    #[test]
+    fn serial_bound() {
+        let model = PlotterModel {
+            baud_rate: 100,
+            ..model()
+        };
+        // `PA1,1;` is 6 chars, at 10 chars/s
+        let program: HpglProgram = vec![HpglCommand::PlotAbsolute(
+            Coordinate { x: 1., y: 1. }.into(),
+        )]
+        .into();
+
+        assert!(model.estimate(&program) > Duration::from_millis(600));
+    }
+}
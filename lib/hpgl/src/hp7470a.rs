@@ -3,6 +3,9 @@ use std::io;
 
 use crate::PlotterWriteable;
 
+/// Fastest the carriage will move, in cm/s. Pen-up moves always run at this speed.
+pub const MAX_VELOCITY: f32 = 38.1;
+
 bitflags! {
     #[derive(Default)]
     pub struct PlotterConfig: u8 {
@@ -49,13 +52,13 @@ impl PlotterWriteable for DeviceControlInstruction {
                 write!(w, "\x1b.@;{}:", config.bits)?;
             }
             SetHandshakeMode(mode, config) => {
-                w.write(b"\x1b.")?;
+                w.write_all(b"\x1b.")?;
                 match mode {
                     HandshakeMode::Mode1 => {
-                        w.write(b"H")?;
+                        w.write_all(b"H")?;
                     }
                     HandshakeMode::Mode2 => {
-                        w.write(b"I")?;
+                        w.write_all(b"I")?;
                     }
                 }
 
@@ -66,7 +69,7 @@ impl PlotterWriteable for DeviceControlInstruction {
                         ack_string,
                     } => {
                         write!(w, "{};{};", block_size, enq_char)?;
-                        w.write(
+                        w.write_all(
                             ack_string
                                 .iter()
                                 .map(|c| c.to_string())
@@ -80,7 +83,7 @@ impl PlotterWriteable for DeviceControlInstruction {
                         xon_trigger_chars,
                     } => {
                         write!(w, "{};;", xoff_threshold)?;
-                        w.write(
+                        w.write_all(
                             xon_trigger_chars
                                 .iter()
                                 .map(|c| c.to_string())
@@ -91,7 +94,7 @@ impl PlotterWriteable for DeviceControlInstruction {
                     }
                 }
 
-                w.write(b":")?;
+                w.write_all(b":")?;
             }
             SetExtHandshakeOptions {
                 interchar_delay,
@@ -101,8 +104,8 @@ impl PlotterWriteable for DeviceControlInstruction {
                 if let Some(interchar_delay) = interchar_delay {
                     write!(w, "{}", interchar_delay)?;
                 }
-                w.write(b";")?;
-                w.write(
+                w.write_all(b";")?;
+                w.write_all(
                     xoff_trigger_chars
                         .iter()
                         .map(|c| c.to_string())
@@ -111,7 +114,7 @@ impl PlotterWriteable for DeviceControlInstruction {
                         .as_bytes(),
                 )?;
 
-                w.write(b":")?;
+                w.write_all(b":")?;
             }
         }
 
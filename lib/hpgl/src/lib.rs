@@ -1,6 +1,12 @@
 use std::io;
 
+pub mod estimate;
 pub mod hp7470a;
+pub mod optimize;
+pub mod parse;
+pub mod sender;
+pub mod simulator;
+pub mod stats;
 
 pub trait PlotterWriteable {
     fn write<W>(&self, sink: &mut W) -> io::Result<()>
@@ -8,12 +14,24 @@ pub trait PlotterWriteable {
         W: io::Write;
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct HpglProgram(Vec<HpglCommand>);
 impl HpglProgram {
     pub fn new(commands: Vec<HpglCommand>) -> Self {
         Self(commands)
     }
+
+    pub fn commands(&self) -> &[HpglCommand] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 impl PlotterWriteable for HpglProgram {
@@ -21,10 +39,7 @@ impl PlotterWriteable for HpglProgram {
     where
         W: io::Write,
     {
-        self.0
-            .iter()
-            .map(|command| command.write(sink))
-            .collect::<_>()
+        self.0.iter().try_for_each(|command| command.write(sink))
     }
 }
 
@@ -37,7 +52,7 @@ impl From<Vec<HpglCommand>> for HpglProgram {
 /// Raw coordinate (can represent either absolute or relative, non-/plotter).
 ///
 /// When plotting in _plotter coordinates_, x ∈ [0, 10900], y ∈ [0, 7650]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Coordinate {
     pub x: f32,
     pub y: f32,
@@ -46,9 +61,11 @@ impl Coordinate {
     pub const MAX_X_A4: f32 = 10900.;
     pub const MAX_X_US: f32 = 10300.;
     pub const MAX_Y: f32 = 7650.;
+    /// One plotter unit is 0.025mm.
+    pub const UNITS_PER_CM: f32 = 400.;
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CoordinateChain(pub Vec<Coordinate>);
 
 impl CoordinateChain {
@@ -59,7 +76,7 @@ impl CoordinateChain {
         let mut iter = self.0.iter().peekable();
         while let Some(coord) = iter.next() {
             write!(sink, "{},{}", coord.x, coord.y)?;
-            if let Some(_) = iter.peek() {
+            if iter.peek().is_some() {
                 write!(sink, ",")?;
             }
         }
@@ -80,7 +97,7 @@ impl From<Coordinate> for CoordinateChain {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum HpglCommand {
     DefaultSettings,
     InitializePlotter,
@@ -105,31 +122,31 @@ impl PlotterWriteable for HpglCommand {
         use HpglCommand::*;
         match self {
             DefaultSettings => {
-                sink.write(b"DF;")?;
+                sink.write_all(b"DF;")?;
             }
             InitializePlotter => {
-                sink.write(b"IN;")?;
+                sink.write_all(b"IN;")?;
             }
             SelectPen { pen } => {
-                sink.write(b"SP")?;
+                sink.write_all(b"SP")?;
                 write!(sink, "{}", pen)?;
-                sink.write(b";")?;
+                sink.write_all(b";")?;
             }
             VelocitySelect { velocity } => {
-                sink.write(b"VS")?;
+                sink.write_all(b"VS")?;
                 write!(sink, "{}", velocity)?;
-                sink.write(b";")?;
+                sink.write_all(b";")?;
             }
             PenUp => {
-                sink.write(b"PU;")?;
+                sink.write_all(b"PU;")?;
             }
             PenDown => {
-                sink.write(b"PD;")?;
+                sink.write_all(b"PD;")?;
             }
             PlotAbsolute(coord) => {
-                sink.write(b"PA")?;
+                sink.write_all(b"PA")?;
                 coord.write(sink)?;
-                sink.write(b";")?;
+                sink.write_all(b";")?;
             }
         }
 
@@ -137,6 +154,31 @@ impl PlotterWriteable for HpglCommand {
     }
 }
 
+/// Commands drawing `points` as one continuous line: travel to the first point with the pen
+/// raised, lower it, plot the rest, and raise it again.
+pub fn polyline<I>(points: I) -> Vec<HpglCommand>
+where
+    I: IntoIterator<Item = Coordinate>,
+{
+    let mut points = points.into_iter();
+    let first = match points.next() {
+        Some(first) => first,
+        None => return Vec::new(),
+    };
+
+    let mut commands = vec![
+        HpglCommand::PlotAbsolute(first.into()),
+        HpglCommand::PenDown,
+    ];
+    let rest: Vec<Coordinate> = points.collect();
+    if !rest.is_empty() {
+        commands.push(HpglCommand::PlotAbsolute(rest.into()));
+    }
+    commands.push(HpglCommand::PenUp);
+
+    commands
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,276 @@
+//! Reorders a program's pen-down strokes to cut down on pen-up travel.
+//!
+//! A program is broken into [`Stroke`]s by replaying it through the simulator, the strokes are
+//! shuffled around, and a fresh program is emitted from them. Pen and velocity changes act as
+//! barriers: strokes are only ever reordered among neighbours drawn with the same settings.
+
+use crate::simulator::PlotterState;
+use crate::{Coordinate, HpglCommand, HpglProgram};
+
+/// A continuous pen-down line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    pub pen: usize,
+    pub velocity: Option<f32>,
+    pub points: Vec<Coordinate>,
+}
+
+impl Stroke {
+    pub fn start(&self) -> Coordinate {
+        self.points[0]
+    }
+
+    pub fn end(&self) -> Coordinate {
+        self.points[self.points.len() - 1]
+    }
+
+    fn same_settings(&self, other: &Stroke) -> bool {
+        self.pen == other.pen && self.velocity == other.velocity
+    }
+}
+
+fn distance(a: Coordinate, b: Coordinate) -> f32 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+/// Splits `program` into the strokes it draws, in order.
+pub fn strokes(program: &HpglProgram) -> Vec<Stroke> {
+    let mut strokes = Vec::new();
+    let mut state = PlotterState::default();
+    let mut current: Option<Stroke> = None;
+
+    for command in program.commands() {
+        let was_down = state.pen_down;
+        let moves = state.apply(command);
+
+        if !was_down && state.pen_down {
+            current = Some(Stroke {
+                pen: state.pen,
+                velocity: state.velocity,
+                points: vec![state.position],
+            });
+        }
+
+        if let Some(stroke) = &mut current {
+            stroke.points.extend(moves.iter().map(|m| m.to));
+
+            // a settings change mid-stroke starts a new one
+            if state.pen != stroke.pen || state.velocity != stroke.velocity {
+                let position = stroke.end();
+                strokes.extend(current.take());
+                if state.pen_down {
+                    current = Some(Stroke {
+                        pen: state.pen,
+                        velocity: state.velocity,
+                        points: vec![position],
+                    });
+                }
+            }
+        }
+
+        if was_down && !state.pen_down {
+            strokes.extend(current.take());
+        }
+    }
+    strokes.extend(current);
+
+    strokes
+}
+
+/// Greedily draws whichever stroke starts (or, reversed, ends) nearest to the pen next.
+pub fn nearest_neighbour(strokes: Vec<Stroke>) -> Vec<Stroke> {
+    let mut ordered: Vec<Stroke> = Vec::with_capacity(strokes.len());
+    let mut remaining = strokes.into_iter().peekable();
+    let mut position = Coordinate { x: 0., y: 0. };
+
+    while let Some(first) = remaining.next() {
+        // gather the run of strokes sharing `first`'s settings
+        let mut run = vec![first];
+        while let Some(next) = remaining.peek() {
+            if !next.same_settings(&run[0]) {
+                break;
+            }
+            run.extend(remaining.next());
+        }
+
+        while !run.is_empty() {
+            let (index, reversed, _) = run
+                .iter()
+                .enumerate()
+                .flat_map(|(i, stroke)| {
+                    vec![
+                        (i, false, distance(position, stroke.start())),
+                        (i, true, distance(position, stroke.end())),
+                    ]
+                })
+                .fold((0, false, f32::INFINITY), |best, candidate| {
+                    if candidate.2 < best.2 {
+                        candidate
+                    } else {
+                        best
+                    }
+                });
+
+            let mut stroke = run.swap_remove(index);
+            if reversed {
+                stroke.points.reverse();
+            }
+            position = stroke.end();
+            ordered.push(stroke);
+        }
+    }
+
+    ordered
+}
+
+/// Sorts strokes by `key` of their starting points, e.g. their position along a space-filling
+/// curve. Much cheaper than [`nearest_neighbour`] for big programs, if not quite as good. Like it,
+/// only reorders within runs of strokes sharing settings.
+pub fn spatial_sort<F>(mut strokes: Vec<Stroke>, mut key: F) -> Vec<Stroke>
+where
+    F: FnMut(Coordinate) -> u64,
+{
+    let mut start = 0;
+    while start < strokes.len() {
+        let end = strokes[start..]
+            .iter()
+            .position(|stroke| !stroke.same_settings(&strokes[start]))
+            .map_or(strokes.len(), |length| start + length);
+        strokes[start..end].sort_by_cached_key(|stroke| key(stroke.start()));
+        start = end;
+    }
+
+    strokes
+}
+
+/// Joins strokes which pick up exactly where the previous one left off.
+pub fn merge(strokes: Vec<Stroke>) -> Vec<Stroke> {
+    let mut merged: Vec<Stroke> = Vec::with_capacity(strokes.len());
+    for stroke in strokes {
+        match merged.last_mut() {
+            Some(last) if last.same_settings(&stroke) && last.end() == stroke.start() => {
+                last.points.extend(stroke.points.into_iter().skip(1));
+            }
+            _ => merged.push(stroke),
+        }
+    }
+
+    merged
+}
+
+/// Emits a program drawing `strokes` in order, starting from a freshly initialized plotter.
+pub fn program(strokes: &[Stroke]) -> HpglProgram {
+    let mut commands = vec![HpglCommand::InitializePlotter];
+    let mut state = PlotterState::default();
+
+    for stroke in strokes {
+        if stroke.pen != state.pen {
+            commands.push(HpglCommand::SelectPen { pen: stroke.pen });
+        }
+        if stroke.velocity != state.velocity {
+            commands.push(match stroke.velocity {
+                Some(velocity) => HpglCommand::VelocitySelect { velocity },
+                None => HpglCommand::DefaultSettings,
+            });
+        }
+        state.pen = stroke.pen;
+        state.velocity = stroke.velocity;
+
+        commands.extend(crate::polyline(stroke.points.iter().cloned()));
+    }
+
+    commands.into()
+}
+
+/// Reorders and merges the strokes in `program`.
+pub fn optimize(program: &HpglProgram) -> HpglProgram {
+    self::program(&merge(nearest_neighbour(strokes(program))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stats::Stats;
+
+    fn line(from: (f32, f32), to: (f32, f32)) -> Vec<HpglCommand> {
+        crate::polyline(vec![
+            Coordinate {
+                x: from.0,
+                y: from.1,
+            },
+            Coordinate { x: to.0, y: to.1 },
+        ])
+    }
+
+    #[test]
+    fn splits_strokes() {
+        let mut commands = vec![
+            HpglCommand::InitializePlotter,
+            HpglCommand::SelectPen { pen: 1 },
+        ];
+        commands.extend(line((0., 0.), (10., 0.)));
+        commands.push(HpglCommand::SelectPen { pen: 2 });
+        commands.extend(line((10., 0.), (10., 10.)));
+
+        let strokes = strokes(&commands.into());
+        assert_eq!(strokes.len(), 2);
+        assert_eq!(strokes[0].pen, 1);
+        assert_eq!(strokes[1].pen, 2);
+        assert_eq!(strokes[1].points.len(), 2);
+    }
+
+    #[test]
+    fn reorders_reverses_and_merges() {
+        let mut commands = vec![
+            HpglCommand::InitializePlotter,
+            HpglCommand::SelectPen { pen: 1 },
+        ];
+        commands.extend(line((0., 0.), (100., 0.)));
+        commands.extend(line((1000., 1000.), (200., 0.)));
+        commands.extend(line((100., 0.), (200., 0.)));
+        let original: HpglProgram = commands.into();
+
+        let optimized = optimize(&original);
+        let strokes = strokes(&optimized);
+        assert_eq!(strokes.len(), 1);
+        assert_eq!(strokes[0].end(), Coordinate { x: 1000., y: 1000. });
+
+        let before = Stats::of(&original);
+        let after = Stats::of(&optimized);
+        assert_eq!(before.pen_down_length, after.pen_down_length);
+        assert!(after.pen_up_travel < before.pen_up_travel);
+    }
+
+    #[test]
+    fn pen_changes_are_barriers() {
+        let mut commands = vec![HpglCommand::SelectPen { pen: 1 }];
+        commands.extend(line((0., 0.), (10., 0.)));
+        commands.push(HpglCommand::SelectPen { pen: 2 });
+        commands.extend(line((500., 500.), (600., 600.)));
+        commands.push(HpglCommand::SelectPen { pen: 1 });
+        commands.extend(line((10., 0.), (20., 0.)));
+
+        let pens: Vec<usize> = nearest_neighbour(strokes(&commands.into()))
+            .iter()
+            .map(|s| s.pen)
+            .collect();
+        assert_eq!(pens, vec![1, 2, 1]);
+    }
+
+    #[test]
+    fn sorts_by_key_within_runs() {
+        let stroke = |pen, x| Stroke {
+            pen,
+            velocity: None,
+            points: vec![Coordinate { x, y: 0. }, Coordinate { x, y: 1. }],
+        };
+        let strokes = vec![stroke(1, 3.), stroke(1, 1.), stroke(2, 9.), stroke(2, 2.)];
+
+        let sorted = spatial_sort(strokes, |c| c.x as u64);
+        let order = sorted
+            .iter()
+            .map(|stroke| (stroke.pen, stroke.start().x))
+            .collect::<Vec<_>>();
+        assert_eq!(order, &[(1, 1.), (1, 3.), (2, 2.), (2, 9.)]);
+    }
+}
@@ -0,0 +1,184 @@
+//! Reads HP-GL text back into an [`HpglProgram`], so saved plots can be replayed or post-processed.
+//!
+//! Only the subset of HP-GL that [`HpglCommand`] can represent is understood. `PU`/`PD` with
+//! coordinates are split into a pen command followed by a [`HpglCommand::PlotAbsolute`].
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Coordinate, HpglCommand, HpglProgram};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Byte offset of the offending command.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+fn is_separator(c: char) -> bool {
+    c == ',' || c.is_whitespace()
+}
+
+fn parse_numbers(offset: usize, params: &str) -> Result<Vec<f32>, ParseError> {
+    params
+        .split(is_separator)
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            p.parse().map_err(|_| ParseError {
+                offset,
+                message: format!("bad number {:?}", p),
+            })
+        })
+        .collect()
+}
+
+fn parse_coordinates(offset: usize, params: &str) -> Result<Vec<Coordinate>, ParseError> {
+    let numbers = parse_numbers(offset, params)?;
+    if numbers.len() % 2 != 0 {
+        return Err(ParseError {
+            offset,
+            message: "odd number of coordinates".to_string(),
+        });
+    }
+
+    Ok(numbers
+        .chunks(2)
+        .map(|xy| Coordinate { x: xy[0], y: xy[1] })
+        .collect())
+}
+
+fn parse_command(
+    offset: usize,
+    mnemonic: &str,
+    params: &str,
+    program: &mut Vec<HpglCommand>,
+) -> Result<(), ParseError> {
+    let single = |default: Option<f32>| -> Result<f32, ParseError> {
+        match (parse_numbers(offset, params)?.as_slice(), default) {
+            ([value], _) => Ok(*value),
+            ([], Some(default)) => Ok(default),
+            _ => Err(ParseError {
+                offset,
+                message: format!("{} takes a single parameter", mnemonic),
+            }),
+        }
+    };
+
+    match mnemonic {
+        "IN" => program.push(HpglCommand::InitializePlotter),
+        "DF" => program.push(HpglCommand::DefaultSettings),
+        "SP" => program.push(HpglCommand::SelectPen {
+            pen: single(Some(0.))? as usize,
+        }),
+        "VS" => program.push(HpglCommand::VelocitySelect {
+            velocity: single(None)?,
+        }),
+        "PU" | "PD" => {
+            program.push(if mnemonic == "PU" {
+                HpglCommand::PenUp
+            } else {
+                HpglCommand::PenDown
+            });
+
+            let coordinates = parse_coordinates(offset, params)?;
+            if !coordinates.is_empty() {
+                program.push(HpglCommand::PlotAbsolute(coordinates.into()));
+            }
+        }
+        "PA" => program.push(HpglCommand::PlotAbsolute(
+            parse_coordinates(offset, params)?.into(),
+        )),
+        _ => {
+            return Err(ParseError {
+                offset,
+                message: format!("unsupported command {:?}", mnemonic),
+            })
+        }
+    }
+
+    Ok(())
+}
+
+impl FromStr for HpglProgram {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut program = Vec::new();
+        let mut rest = s;
+
+        loop {
+            rest = rest.trim_start_matches(|c: char| c == ';' || c.is_whitespace());
+            if rest.is_empty() {
+                break;
+            }
+
+            let offset = s.len() - rest.len();
+            if rest.len() < 2 || !rest.is_char_boundary(2) {
+                return Err(ParseError {
+                    offset,
+                    message: "truncated command".to_string(),
+                });
+            }
+            let mnemonic = rest[..2].to_ascii_uppercase();
+            if !mnemonic.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(ParseError {
+                    offset,
+                    message: format!("expected a command, found {:?}", mnemonic),
+                });
+            }
+
+            // parameters run until a terminator or the next mnemonic
+            rest = &rest[2..];
+            let end = rest
+                .find(|c: char| c == ';' || c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            parse_command(offset, &mnemonic, &rest[..end], &mut program)?;
+            rest = &rest[end..];
+        }
+
+        Ok(program.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PlotterWriteable;
+
+    #[test]
+    fn roundtrip() {
+        let source = "IN;SP1;VS10;PA10,10;PD;PA20,10,20,20;PU;";
+        let program: HpglProgram = source.parse().unwrap();
+        assert_eq!(program.len(), 7);
+
+        let mut buf: Vec<u8> = Vec::new();
+        program.write(&mut buf).unwrap();
+        assert_eq!(buf, source.as_bytes());
+    }
+
+    #[test]
+    fn pen_moves_and_loose_syntax() {
+        let program: HpglProgram = "in\nsp 2\nPU 10 10 PD20,20;pu".parse().unwrap();
+
+        let mut buf: Vec<u8> = Vec::new();
+        program.write(&mut buf).unwrap();
+        assert_eq!(buf, b"IN;SP2;PU;PA10,10;PD;PA20,20;PU;");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!("IN;PR10,10;".parse::<HpglProgram>().unwrap_err().offset, 3);
+        assert!("PA10;".parse::<HpglProgram>().is_err());
+        assert!("VS;".parse::<HpglProgram>().is_err());
+        assert!("SP1.5.5;".parse::<HpglProgram>().is_err());
+    }
+}
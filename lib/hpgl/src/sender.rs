@@ -0,0 +1,463 @@
+//! Streams a program to a plotter one command at a time, so an interrupted plot can be picked
+//! back up where it left off instead of restarted from scratch.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::estimate::PlotterModel;
+use crate::simulator::PlotterState;
+use crate::{HpglCommand, HpglProgram, PlotterWriteable};
+
+/// On-disk record of how far into a program the plotter got.
+///
+/// Stored as `<acknowledged>/<total>`, where `acknowledged` is the number of commands the plotter
+/// has confirmed it executed. Commands written after that may still have been sitting in the
+/// serial driver's or the plotter's buffer, so they're sent again on resume.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+}
+
+impl Checkpoint {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the number of acknowledged commands, or `None` if there's no checkpoint on disk.
+    ///
+    /// Fails if the checkpoint was recorded for a program of a different length than `total`.
+    pub fn load(&self, total: usize) -> io::Result<Option<usize>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let malformed = || io::Error::new(io::ErrorKind::InvalidData, "malformed checkpoint");
+        let mut fields = contents.trim().splitn(2, '/');
+        let acknowledged: usize = fields
+            .next()
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(malformed)?;
+        let recorded_total: usize = fields
+            .next()
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(malformed)?;
+
+        if recorded_total != total || acknowledged > total {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "checkpoint is for a program of {} commands, not {}",
+                    recorded_total, total
+                ),
+            ));
+        }
+
+        Ok(Some(acknowledged))
+    }
+
+    pub fn store(&self, acknowledged: usize, total: usize) -> io::Result<()> {
+        // write-then-rename, so a crash mid-write can't leave a truncated checkpoint behind
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, format!("{}/{}\n", acknowledged, total))?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// Snapshot handed to [`Sender::on_progress`] callbacks after each command goes out.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    /// Index one past the last command sent, counted from the start of the program.
+    pub commands_sent: usize,
+    pub commands_total: usize,
+    /// Bytes written this session, including any resume preamble.
+    pub bytes_sent: usize,
+    /// Pen-down distance covered so far, in plotter units.
+    pub pen_down_distance: f32,
+    /// Modeled time left to draw the rest of the program.
+    pub remaining: Duration,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f32 {
+        if self.commands_total == 0 {
+            1.
+        } else {
+            self.commands_sent as f32 / self.commands_total as f32
+        }
+    }
+}
+
+type ProgressCallback<'p> = Box<dyn FnMut(&Progress) + 'p>;
+
+/// Commands between checkpoints, unless set with [`Sender::checkpoint_every`].
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 100;
+
+/// Asks for the pen's actual position. The plotter only answers once it has worked through
+/// everything before it, so the reply confirms those commands were executed.
+const OUTPUT_ACTUAL_POSITION: &[u8] = b"OA;";
+
+/// Sends `OA;` and waits for the carriage-return-terminated reply.
+fn acknowledge<P>(port: &mut P) -> io::Result<()>
+where
+    P: Read + Write,
+{
+    port.write_all(OUTPUT_ACTUAL_POSITION)?;
+    port.flush()?;
+
+    let mut byte = [0u8];
+    loop {
+        match port.read(&mut byte) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "plotter stopped answering",
+                ))
+            }
+            Ok(_) if byte[0] == b'\r' => return Ok(()),
+            Ok(_) => {}
+            // long strokes can keep the plotter busy for longer than the port's timeout
+            Err(e)
+                if e.kind() == io::ErrorKind::TimedOut
+                    || e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+pub struct Sender<'p> {
+    program: &'p HpglProgram,
+    checkpoint: Option<Checkpoint>,
+    checkpoint_interval: usize,
+    start: usize,
+    model: PlotterModel,
+    on_progress: Option<ProgressCallback<'p>>,
+}
+
+impl<'p> Sender<'p> {
+    pub fn new(program: &'p HpglProgram) -> Self {
+        Self {
+            program,
+            checkpoint: None,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            start: 0,
+            model: PlotterModel::default(),
+            on_progress: None,
+        }
+    }
+
+    /// Records progress to `checkpoint` every so many commands, once the plotter has confirmed
+    /// it got that far. The checkpoint is removed once the whole program has been sent.
+    pub fn checkpoint(mut self, checkpoint: Checkpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// How many commands go out between checkpoints. Each one waits for the plotter to catch
+    /// up, so too few stalls the plot while too many repeats more of it after a crash.
+    pub fn checkpoint_every(mut self, commands: usize) -> Self {
+        self.checkpoint_interval = commands.max(1);
+        self
+    }
+
+    /// Plotter model used to work out [`Progress::remaining`].
+    pub fn model(mut self, model: PlotterModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Calls `callback` after every command is written.
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&Progress) + 'p,
+    {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Skips the first `index` commands, restoring the plotter state they would've left behind.
+    pub fn resume_from(mut self, index: usize) -> Self {
+        self.start = index.min(self.program.len());
+        self
+    }
+
+    /// Resumes from the attached checkpoint, if there is one on disk.
+    pub fn resume_from_checkpoint(self) -> io::Result<Self> {
+        let acknowledged = match &self.checkpoint {
+            Some(checkpoint) => checkpoint.load(self.program.len())?,
+            None => None,
+        };
+
+        Ok(match acknowledged {
+            Some(index) => self.resume_from(index),
+            None => self,
+        })
+    }
+
+    /// Commands which bring a freshly-initialized plotter into the state it was in just before
+    /// command `start`: right pen, right speed, right place, pen raised while travelling there.
+    pub fn resume_preamble(&self) -> Vec<HpglCommand> {
+        if self.start == 0 {
+            return Vec::new();
+        }
+
+        let state = PlotterState::after(&self.program.commands()[..self.start]);
+        let mut preamble = vec![HpglCommand::InitializePlotter];
+        if state.pen != 0 {
+            preamble.push(HpglCommand::SelectPen { pen: state.pen });
+        }
+        if let Some(velocity) = state.velocity {
+            preamble.push(HpglCommand::VelocitySelect { velocity });
+        }
+        preamble.push(HpglCommand::PenUp);
+        preamble.push(HpglCommand::PlotAbsolute(state.position.into()));
+        if state.pen_down {
+            preamble.push(HpglCommand::PenDown);
+        }
+
+        preamble
+    }
+
+    /// Streams the program to `port`, which is read from only for the plotter's replies when
+    /// checkpointing.
+    pub fn send<P>(&mut self, port: &mut P) -> io::Result<()>
+    where
+        P: Read + Write,
+    {
+        let mut buf: Vec<u8> = Vec::new();
+        for command in self.resume_preamble() {
+            command.write(&mut buf)?;
+        }
+        port.write_all(&buf)?;
+        port.flush()?;
+
+        // per-command pen-down distance and modeled duration, from the simulator
+        let mut state = PlotterState::default();
+        let costs: Vec<(f32, Duration)> = self
+            .program
+            .commands()
+            .iter()
+            .map(|command| {
+                let distance = state
+                    .clone()
+                    .apply(command)
+                    .iter()
+                    .filter(|m| m.pen_down)
+                    .map(|m| m.length())
+                    .sum();
+                (distance, self.model.command_duration(&mut state, command))
+            })
+            .collect();
+
+        let mut progress = Progress {
+            commands_sent: self.start,
+            commands_total: self.program.len(),
+            bytes_sent: buf.len(),
+            pen_down_distance: costs[..self.start].iter().map(|c| c.0).sum(),
+            remaining: costs[self.start..].iter().map(|c| c.1).sum(),
+        };
+
+        let total = self.program.len();
+        let remaining = self.program.commands()[self.start..].iter();
+        for ((index, command), &(distance, duration)) in
+            (self.start..).zip(remaining).zip(&costs[self.start..])
+        {
+            buf.clear();
+            command.write(&mut buf)?;
+            port.write_all(&buf)?;
+            port.flush()?;
+            progress.bytes_sent += buf.len();
+
+            if let Some(checkpoint) = &self.checkpoint {
+                if (index + 1) % self.checkpoint_interval == 0 && index + 1 < total {
+                    acknowledge(port)?;
+                    progress.bytes_sent += OUTPUT_ACTUAL_POSITION.len();
+                    checkpoint.store(index + 1, total)?;
+                }
+            }
+
+            if let Some(callback) = &mut self.on_progress {
+                progress.commands_sent = index + 1;
+                progress.pen_down_distance += distance;
+                progress.remaining = progress.remaining.checked_sub(duration).unwrap_or_default();
+                callback(&progress);
+            }
+        }
+
+        if let Some(checkpoint) = &self.checkpoint {
+            checkpoint.clear()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Coordinate;
+
+    /// the other end of the line: records what it's sent, and answers `OA;` until it runs out
+    /// of answers.
+    struct Plotter {
+        received: Vec<u8>,
+        answers: usize,
+        replies: Vec<u8>,
+    }
+
+    impl Plotter {
+        fn answering(answers: usize) -> Self {
+            Self {
+                received: Vec::new(),
+                answers,
+                replies: Vec::new(),
+            }
+        }
+    }
+
+    impl Write for Plotter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf == OUTPUT_ACTUAL_POSITION && self.answers > 0 {
+                self.answers -= 1;
+                self.replies.extend(b"0,0,1\r");
+            }
+            self.received.extend(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for Plotter {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.replies.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.replies[..n]);
+            self.replies.drain(..n);
+            Ok(n)
+        }
+    }
+
+    fn program() -> HpglProgram {
+        vec![
+            HpglCommand::InitializePlotter,
+            HpglCommand::SelectPen { pen: 1 },
+            HpglCommand::PlotAbsolute(Coordinate { x: 10., y: 10. }.into()),
+            HpglCommand::PenDown,
+            HpglCommand::PlotAbsolute(Coordinate { x: 20., y: 10. }.into()),
+            HpglCommand::PlotAbsolute(Coordinate { x: 20., y: 20. }.into()),
+            HpglCommand::PenUp,
+        ]
+        .into()
+    }
+
+    #[test]
+    fn send_whole_program() {
+        let program = program();
+        let mut plotter = Plotter::answering(0);
+        Sender::new(&program).send(&mut plotter).unwrap();
+
+        assert_eq!(plotter.received, b"IN;SP1;PA10,10;PD;PA20,10;PA20,20;PU;");
+    }
+
+    #[test]
+    fn resume_mid_stroke() {
+        let program = program();
+        let mut plotter = Plotter::answering(0);
+        Sender::new(&program)
+            .resume_from(5)
+            .send(&mut plotter)
+            .unwrap();
+
+        assert_eq!(plotter.received, b"IN;SP1;PU;PA20,10;PD;PA20,20;PU;");
+    }
+
+    #[test]
+    fn checkpoint_roundtrip() {
+        let path = std::env::temp_dir().join(format!("hpgl-checkpoint-{}", std::process::id()));
+        let checkpoint = Checkpoint::new(&path);
+
+        assert_eq!(checkpoint.load(7).unwrap(), None);
+        checkpoint.store(3, 7).unwrap();
+        assert_eq!(checkpoint.load(7).unwrap(), Some(3));
+        assert!(checkpoint.load(8).is_err());
+
+        let program = program();
+        let mut plotter = Plotter::answering(0);
+        Sender::new(&program)
+            .checkpoint(checkpoint.clone())
+            .resume_from_checkpoint()
+            .unwrap()
+            .send(&mut plotter)
+            .unwrap();
+
+        assert_eq!(
+            plotter.received,
+            b"IN;SP1;PU;PA10,10;PD;PA20,10;PA20,20;PU;"
+        );
+        assert_eq!(checkpoint.load(7).unwrap(), None);
+    }
+
+    #[test]
+    fn checkpoints_wait_for_the_plotter() {
+        let path = std::env::temp_dir().join(format!("hpgl-checkpoint-ack-{}", std::process::id()));
+        let checkpoint = Checkpoint::new(&path);
+        let program = program();
+
+        // the plotter confirms the first two commands, then goes quiet
+        let mut plotter = Plotter::answering(1);
+        let sent = Sender::new(&program)
+            .checkpoint(checkpoint.clone())
+            .checkpoint_every(2)
+            .send(&mut plotter);
+        assert!(sent.is_err());
+        assert_eq!(plotter.received, b"IN;SP1;OA;PA10,10;PD;OA;");
+        assert_eq!(checkpoint.load(7).unwrap(), Some(2));
+
+        // so the unconfirmed ones go again, and no checkpoint is needed after the last
+        let mut plotter = Plotter::answering(usize::MAX);
+        Sender::new(&program)
+            .checkpoint(checkpoint.clone())
+            .checkpoint_every(2)
+            .resume_from_checkpoint()
+            .unwrap()
+            .send(&mut plotter)
+            .unwrap();
+        assert_eq!(
+            plotter.received,
+            b"IN;SP1;PU;PA0,0;PA10,10;PD;OA;PA20,10;PA20,20;OA;PU;"
+        );
+        assert_eq!(checkpoint.load(7).unwrap(), None);
+    }
+
+    #[test]
+    fn progress_reports() {
+        let program = program();
+        let mut plotter = Plotter::answering(0);
+        let mut reports = Vec::new();
+        Sender::new(&program)
+            .on_progress(|progress| reports.push(progress.clone()))
+            .send(&mut plotter)
+            .unwrap();
+
+        assert_eq!(reports.len(), program.len());
+        let last = reports.last().unwrap();
+        assert_eq!(last.commands_sent, 7);
+        assert_eq!(last.bytes_sent, plotter.received.len());
+        assert_eq!(last.pen_down_distance, 20.);
+        assert_eq!(last.remaining, Duration::default());
+        assert!(reports[0].remaining > Duration::default());
+    }
+}
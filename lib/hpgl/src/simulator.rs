@@ -0,0 +1,154 @@
+//! Tracks what the plotter _would_ be doing as a program executes, without a plotter attached.
+
+use crate::{Coordinate, HpglCommand};
+
+/// Snapshot of the plotter's state between two commands.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlotterState {
+    pub position: Coordinate,
+    pub pen_down: bool,
+    /// Currently selected pen; `0` means no pen is in the holder.
+    pub pen: usize,
+    /// Pen-down velocity set by `VS`, in cm/s. `None` means the plotter's default.
+    pub velocity: Option<f32>,
+}
+
+impl Default for PlotterState {
+    fn default() -> Self {
+        Self {
+            position: Coordinate { x: 0., y: 0. },
+            pen_down: false,
+            pen: 0,
+            velocity: None,
+        }
+    }
+}
+
+/// A single straight-line carriage movement.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Move {
+    pub from: Coordinate,
+    pub to: Coordinate,
+    pub pen_down: bool,
+    pub pen: usize,
+    pub velocity: Option<f32>,
+}
+
+impl Move {
+    /// Length in plotter units.
+    pub fn length(&self) -> f32 {
+        (self.to.x - self.from.x).hypot(self.to.y - self.from.y)
+    }
+}
+
+impl PlotterState {
+    /// Replays `commands` from power-on.
+    pub fn after<'a, I>(commands: I) -> Self
+    where
+        I: IntoIterator<Item = &'a HpglCommand>,
+    {
+        let mut state = Self::default();
+        for command in commands {
+            state.apply(command);
+        }
+
+        state
+    }
+
+    /// Steps the state past `command`, returning the carriage movements it caused.
+    pub fn apply(&mut self, command: &HpglCommand) -> Vec<Move> {
+        use HpglCommand::*;
+        match command {
+            DefaultSettings => {
+                self.velocity = None;
+            }
+            InitializePlotter => {
+                self.pen_down = false;
+                self.velocity = None;
+            }
+            SelectPen { pen } => {
+                self.pen = *pen;
+            }
+            VelocitySelect { velocity } => {
+                self.velocity = Some(*velocity);
+            }
+            PenUp => {
+                self.pen_down = false;
+            }
+            PenDown => {
+                self.pen_down = true;
+            }
+            PlotAbsolute(chain) => {
+                let moves = chain
+                    .0
+                    .iter()
+                    .map(|&to| {
+                        let from = self.position;
+                        self.position = to;
+                        Move {
+                            from,
+                            to,
+                            pen_down: self.pen_down,
+                            pen: self.pen,
+                            velocity: self.velocity,
+                        }
+                    })
+                    .collect();
+
+                return moves;
+            }
+        }
+
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tracks_pen_and_position() {
+        let commands = vec![
+            HpglCommand::InitializePlotter,
+            HpglCommand::SelectPen { pen: 2 },
+            HpglCommand::VelocitySelect { velocity: 10. },
+            HpglCommand::PlotAbsolute(Coordinate { x: 100., y: 200. }.into()),
+            HpglCommand::PenDown,
+            HpglCommand::PlotAbsolute(
+                vec![
+                    Coordinate { x: 300., y: 200. },
+                    Coordinate { x: 300., y: 400. },
+                ]
+                .into(),
+            ),
+        ];
+
+        let state = PlotterState::after(&commands);
+        assert_eq!(state.position, Coordinate { x: 300., y: 400. });
+        assert!(state.pen_down);
+        assert_eq!(state.pen, 2);
+        assert_eq!(state.velocity, Some(10.));
+    }
+
+    #[test]
+    fn moves_carry_pen_and_velocity() {
+        let mut state = PlotterState::default();
+        state.apply(&HpglCommand::VelocitySelect { velocity: 4. });
+
+        let travel = state.apply(&HpglCommand::PlotAbsolute(
+            Coordinate { x: 0., y: 400. }.into(),
+        ));
+        state.apply(&HpglCommand::PenDown);
+        let stroke = state.apply(&HpglCommand::PlotAbsolute(
+            Coordinate { x: 300., y: 800. }.into(),
+        ));
+
+        assert_eq!(travel.len(), 1);
+        assert_eq!(travel[0].length(), 400.);
+        assert!(!travel[0].pen_down);
+        assert_eq!(stroke[0].length(), 500.);
+        assert!(stroke[0].pen_down);
+        assert_eq!(stroke[0].velocity, Some(4.));
+    }
+}
@@ -0,0 +1,196 @@
+//! Summary statistics for a program, derived from the simulator's output.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::simulator::PlotterState;
+use crate::{Coordinate, HpglCommand, HpglProgram, PlotterWriteable};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Coordinate,
+    pub max: Coordinate,
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> f32 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max.y - self.min.y
+    }
+
+    pub fn area(&self) -> f32 {
+        self.width() * self.height()
+    }
+
+    fn include(bounds: Option<Self>, point: Coordinate) -> Option<Self> {
+        Some(match bounds {
+            None => Self {
+                min: point,
+                max: point,
+            },
+            Some(Self { min, max }) => Self {
+                min: Coordinate {
+                    x: min.x.min(point.x),
+                    y: min.y.min(point.y),
+                },
+                max: Coordinate {
+                    x: max.x.max(point.x),
+                    y: max.y.max(point.y),
+                },
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub commands: usize,
+    /// Size of the program as sent down the wire.
+    pub bytes: usize,
+    /// Pen-down distance for each pen, in plotter units.
+    pub pen_down_length: BTreeMap<usize, f32>,
+    /// Pen-up distance, in plotter units.
+    pub pen_up_travel: f32,
+    pub pen_lifts: usize,
+    /// Extent of everything drawn with the pen down; `None` if nothing was.
+    pub bounds: Option<BoundingBox>,
+}
+
+impl Stats {
+    /// Line width of a typical fiber-tip plotter pen (0.3mm), in plotter units.
+    pub const PEN_WIDTH: f32 = 12.;
+
+    pub fn of(program: &HpglProgram) -> Self {
+        let mut stats = Self::default();
+        let mut state = PlotterState::default();
+        let mut buf: Vec<u8> = Vec::new();
+
+        for command in program.commands() {
+            stats.commands += 1;
+
+            buf.clear();
+            command
+                .write(&mut buf)
+                .expect("writing to a Vec can't fail");
+            stats.bytes += buf.len();
+
+            if let HpglCommand::PenUp = command {
+                if state.pen_down {
+                    stats.pen_lifts += 1;
+                }
+            }
+
+            for m in state.apply(command) {
+                if m.pen_down {
+                    *stats.pen_down_length.entry(m.pen).or_default() += m.length();
+                    stats.bounds = BoundingBox::include(stats.bounds, m.from);
+                    stats.bounds = BoundingBox::include(stats.bounds, m.to);
+                } else {
+                    stats.pen_up_travel += m.length();
+                }
+            }
+        }
+
+        stats
+    }
+
+    pub fn total_pen_down_length(&self) -> f32 {
+        self.pen_down_length.values().sum()
+    }
+
+    /// Rough fraction of the bounding box covered in ink, for lines `pen_width` plotter units
+    /// wide. Overlapping strokes are counted twice, so this can exceed 1.
+    pub fn ink_coverage(&self, pen_width: f32) -> f32 {
+        match self.bounds {
+            Some(bounds) if bounds.area() > 0. => {
+                self.total_pen_down_length() * pen_width / bounds.area()
+            }
+            _ => 0.,
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cm = |units: f32| units / Coordinate::UNITS_PER_CM;
+
+        writeln!(f, "commands:      {}", self.commands)?;
+        writeln!(f, "bytes:         {}", self.bytes)?;
+        for (pen, length) in &self.pen_down_length {
+            writeln!(f, "pen {} drawn:   {:.1} cm", pen, cm(*length))?;
+        }
+        writeln!(f, "pen-up travel: {:.1} cm", cm(self.pen_up_travel))?;
+        writeln!(f, "pen lifts:     {}", self.pen_lifts)?;
+        match self.bounds {
+            Some(bounds) => writeln!(
+                f,
+                "bounds:        ({}, {}) to ({}, {}), {:.1} x {:.1} cm",
+                bounds.min.x,
+                bounds.min.y,
+                bounds.max.x,
+                bounds.max.y,
+                cm(bounds.width()),
+                cm(bounds.height()),
+            )?,
+            None => writeln!(f, "bounds:        nothing drawn")?,
+        }
+        write!(
+            f,
+            "ink coverage:  {:.1}%",
+            self.ink_coverage(Self::PEN_WIDTH) * 100.
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn square_on_two_pens() {
+        let program: HpglProgram = vec![
+            HpglCommand::InitializePlotter,
+            HpglCommand::SelectPen { pen: 1 },
+            HpglCommand::PlotAbsolute(Coordinate { x: 100., y: 100. }.into()),
+            HpglCommand::PenDown,
+            HpglCommand::PlotAbsolute(
+                vec![
+                    Coordinate { x: 200., y: 100. },
+                    Coordinate { x: 200., y: 200. },
+                ]
+                .into(),
+            ),
+            HpglCommand::PenUp,
+            HpglCommand::SelectPen { pen: 2 },
+            HpglCommand::PenDown,
+            HpglCommand::PlotAbsolute(
+                vec![
+                    Coordinate { x: 100., y: 200. },
+                    Coordinate { x: 100., y: 100. },
+                ]
+                .into(),
+            ),
+            HpglCommand::PenUp,
+            HpglCommand::PenUp,
+        ]
+        .into();
+
+        let stats = Stats::of(&program);
+        assert_eq!(stats.commands, 11);
+        assert_eq!(stats.pen_down_length[&1], 200.);
+        assert_eq!(stats.pen_down_length[&2], 200.);
+        assert_eq!(stats.pen_up_travel, 100f32.hypot(100.));
+        assert_eq!(stats.pen_lifts, 2);
+        assert_eq!(
+            stats.bounds,
+            Some(BoundingBox {
+                min: Coordinate { x: 100., y: 100. },
+                max: Coordinate { x: 200., y: 200. },
+            })
+        );
+        assert_eq!(stats.ink_coverage(10.), 0.4);
+    }
+}
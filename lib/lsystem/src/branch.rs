@@ -0,0 +1,160 @@
+//! branching structure: strings where a pair of symbols (usually `[` and `]`) push and pop the
+//! turtle, so that what's between them hangs off the main line as a branch.
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BracketError {
+    /// a pop, at this index, with nothing to pop.
+    Unopened(usize),
+    /// a push, at this index, that's never popped.
+    Unclosed(usize),
+}
+
+impl fmt::Display for BracketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BracketError::Unopened(position) => write!(f, "at {}: unmatched pop", position),
+            BracketError::Unclosed(position) => {
+                write!(f, "at {}: branch is never closed", position)
+            }
+        }
+    }
+}
+
+impl Error for BracketError {}
+
+/// the index of every bracket's partner, or an error if they don't pair up. entries for
+/// non-bracket symbols are meaningless.
+pub fn partners<Symbol>(
+    state: &[Symbol],
+    push: &Symbol,
+    pop: &Symbol,
+) -> Result<Vec<usize>, BracketError>
+where
+    Symbol: Eq,
+{
+    let mut partners = vec![0; state.len()];
+    let mut open = Vec::new();
+    for (i, symbol) in state.iter().enumerate() {
+        if symbol == push {
+            open.push(i);
+        } else if symbol == pop {
+            let opened = open.pop().ok_or(BracketError::Unopened(i))?;
+            partners[opened] = i;
+            partners[i] = opened;
+        }
+    }
+
+    match open.pop() {
+        Some(unclosed) => Err(BracketError::Unclosed(unclosed)),
+        None => Ok(partners),
+    }
+}
+
+/// like `partners`, but doing the best it can with unbalanced strings: unmatched pops are
+/// partnered with the start of the string, and unclosed pushes with its end.
+pub(crate) fn lenient_partners<Symbol>(state: &[Symbol], push: &Symbol, pop: &Symbol) -> Vec<usize>
+where
+    Symbol: Eq,
+{
+    let mut partners = vec![0; state.len()];
+    let mut open = Vec::new();
+    for (i, symbol) in state.iter().enumerate() {
+        if symbol == push {
+            open.push(i);
+        } else if symbol == pop {
+            if let Some(opened) = open.pop() {
+                partners[opened] = i;
+                partners[i] = opened;
+            }
+        }
+    }
+    for unclosed in open {
+        partners[unclosed] = state.len();
+    }
+
+    partners
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node<Symbol> {
+    Symbol(Symbol),
+    /// everything between a push and its pop.
+    Branch(Vec<Node<Symbol>>),
+}
+
+/// the string as a tree, with each branch nested under the line it grows from.
+pub fn tree<Symbol>(
+    state: &[Symbol],
+    push: &Symbol,
+    pop: &Symbol,
+) -> Result<Vec<Node<Symbol>>, BracketError>
+where
+    Symbol: Eq + Clone,
+{
+    let mut stack: Vec<(usize, Vec<Node<Symbol>>)> = Vec::new();
+    let mut nodes = Vec::new();
+    for (i, symbol) in state.iter().enumerate() {
+        if symbol == push {
+            stack.push((i, std::mem::take(&mut nodes)));
+        } else if symbol == pop {
+            let (_, mut parent) = stack.pop().ok_or(BracketError::Unopened(i))?;
+            parent.push(Node::Branch(nodes));
+            nodes = parent;
+        } else {
+            nodes.push(Node::Symbol(symbol.clone()));
+        }
+    }
+
+    match stack.pop() {
+        Some((unclosed, _)) => Err(BracketError::Unclosed(unclosed)),
+        None => Ok(nodes),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn pairs_up_brackets() {
+        let state = chars("F[+F[-F]F]F[F]");
+        let partners = partners(&state, &'[', &']').unwrap();
+        assert_eq!((partners[1], partners[9]), (9, 1));
+        assert_eq!((partners[4], partners[7]), (7, 4));
+        assert_eq!((partners[11], partners[13]), (13, 11));
+
+        assert_eq!(
+            super::partners(&chars("F]["), &'[', &']'),
+            Err(BracketError::Unopened(1))
+        );
+        assert_eq!(
+            super::partners(&chars("[F[F]"), &'[', &']'),
+            Err(BracketError::Unclosed(0))
+        );
+    }
+
+    #[test]
+    fn builds_a_tree() {
+        use Node::*;
+
+        assert_eq!(
+            tree(&chars("F[+F[G]]H"), &'[', &']'),
+            Ok(vec![
+                Symbol('F'),
+                Branch(vec![Symbol('+'), Symbol('F'), Branch(vec![Symbol('G')])]),
+                Symbol('H'),
+            ])
+        );
+        assert_eq!(
+            tree(&chars("F[G"), &'[', &']'),
+            Err(BracketError::Unclosed(1))
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! context-sensitive rules, written `left < predecessor > right -> successor`.
+//!
+//! context is looked for among a symbol's neighbours, skipping over any symbols in the system's
+//! ignore-set (usually turtle turns). when the system knows its bracket symbols, lookup follows
+//! the branching structure: the left context of a symbol at the start of a branch is whatever
+//! precedes the branch, sibling branches are skipped over, and right context may be found
+//! either inside a branch or past it.
+
+use crate::branch;
+use core::hash::Hash;
+use std::collections::HashSet;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextRule<Symbol> {
+    /// required left context, nearest symbol last. may be empty.
+    pub left: Vec<Symbol>,
+    pub predecessor: Symbol,
+    /// required right context, nearest symbol first. may be empty.
+    pub right: Vec<Symbol>,
+    pub successor: Vec<Symbol>,
+}
+
+impl<Symbol> ContextRule<Symbol>
+where
+    Symbol: Eq + Hash + Clone,
+{
+    pub(crate) fn matches(&self, neighbourhood: &Neighbourhood<Symbol>, index: usize) -> bool {
+        neighbourhood.state[index] == self.predecessor
+            && neighbourhood.left_matches(index, &self.left)
+            && neighbourhood.right_matches(index + 1, &self.right)
+    }
+}
+
+/// a string, and how to look around a position in it.
+pub(crate) struct Neighbourhood<'a, Symbol> {
+    state: &'a [Symbol],
+    ignore: &'a HashSet<Symbol>,
+    /// `(push, pop)`
+    brackets: Option<&'a (Symbol, Symbol)>,
+    /// see `branch::lenient_partners`; empty without brackets.
+    partners: Vec<usize>,
+}
+
+impl<'a, Symbol> Neighbourhood<'a, Symbol>
+where
+    Symbol: Eq + Hash,
+{
+    pub fn new(
+        state: &'a [Symbol],
+        ignore: &'a HashSet<Symbol>,
+        brackets: Option<&'a (Symbol, Symbol)>,
+    ) -> Self {
+        let partners = match brackets {
+            Some((push, pop)) => branch::lenient_partners(state, push, pop),
+            None => Vec::new(),
+        };
+
+        Self {
+            state,
+            ignore,
+            brackets,
+            partners,
+        }
+    }
+
+    fn is_push(&self, symbol: &Symbol) -> bool {
+        matches!(self.brackets, Some((push, _)) if push == symbol)
+    }
+
+    fn is_pop(&self, symbol: &Symbol) -> bool {
+        matches!(self.brackets, Some((_, pop)) if pop == symbol)
+    }
+
+    /// does `left` appear immediately before `index`?
+    pub fn left_matches(&self, index: usize, left: &[Symbol]) -> bool {
+        let mut j = index;
+        for expected in left.iter().rev() {
+            loop {
+                if j == 0 {
+                    return false;
+                }
+                j -= 1;
+
+                let symbol = &self.state[j];
+                if self.ignore.contains(symbol) || self.is_push(symbol) {
+                    // climbing out of a branch into its parent
+                    continue;
+                }
+                if self.is_pop(symbol) {
+                    // a sibling branch; hop over it
+                    j = self.partners[j];
+                    continue;
+                }
+                if symbol == expected {
+                    break;
+                }
+
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// does `right` appear starting at `index`?
+    pub fn right_matches(&self, index: usize, right: &[Symbol]) -> bool {
+        let expected = match right.first() {
+            Some(expected) => expected,
+            None => return true,
+        };
+
+        let mut j = index;
+        while j < self.state.len() {
+            let symbol = &self.state[j];
+            if self.ignore.contains(symbol) {
+                j += 1;
+            } else if self.is_push(symbol) {
+                // look down the branch, and failing that, past it
+                if self.right_matches(j + 1, right) {
+                    return true;
+                }
+                j = self.partners[j] + 1;
+            } else if self.is_pop(symbol) {
+                // the branch we're in ends here
+                return false;
+            } else {
+                return symbol == expected && self.right_matches(j + 1, &right[1..]);
+            }
+        }
+
+        false
+    }
+}
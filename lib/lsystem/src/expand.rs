@@ -0,0 +1,141 @@
+//! depth-first expansion, for when the string at generation n is too big to hold in memory.
+//!
+//! rather than rewriting the whole state once per generation, each symbol is rewritten all the
+//! way down to generation n before moving on to the next, so only a stack of positions within
+//! productions is kept: memory grows with the number of generations, not the string's length.
+
+use crate::{ParametricLSystem, ProductionRules};
+use core::hash::Hash;
+use std::error::Error;
+use std::fmt;
+use std::slice;
+
+/// the system has context-sensitive rules, which need the whole of the previous generation to
+/// match against, so it can't be expanded lazily.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContextSensitive;
+
+impl fmt::Display for ContextSensitive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "can't lazily expand a system with context-sensitive rules; evolve it instead"
+        )
+    }
+}
+
+impl Error for ContextSensitive {}
+
+pub struct Expansion<'a, Symbol> {
+    rules: &'a ProductionRules<Symbol>,
+    generations: usize,
+    /// where we are in each production on the way down, with the generation it belongs to.
+    stack: Vec<(slice::Iter<'a, Symbol>, usize)>,
+}
+
+impl<'a, Symbol> Iterator for Expansion<'a, Symbol>
+where
+    Symbol: Eq + Hash,
+{
+    type Item = &'a Symbol;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (symbols, generation) = self.stack.last_mut()?;
+            let generation = *generation;
+
+            let symbol = match symbols.next() {
+                Some(symbol) => symbol,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            if generation < self.generations {
+                if let Some(replacement) = self.rules.get(symbol) {
+                    self.stack.push((replacement.iter(), generation + 1));
+                    continue;
+                }
+            }
+
+            return Some(symbol);
+        }
+    }
+}
+
+impl<Symbol> ParametricLSystem<Symbol>
+where
+    Symbol: Eq + Hash + Clone,
+{
+    /// lazily yields what `state` would be after `generations` calls to `evolve`, without
+    /// building it.
+    ///
+    /// context-sensitive rules need the whole of the previous generation to match against, so
+    /// systems that have any must be evolved instead.
+    pub fn expand(&self, generations: usize) -> Result<Expansion<'_, Symbol>, ContextSensitive> {
+        if !self.context_rules.is_empty() {
+            return Err(ContextSensitive);
+        }
+
+        Ok(Expansion {
+            rules: &self.rules,
+            generations,
+            stack: vec![(self.state.iter(), 0)],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ContextSensitive;
+    use crate::context::ContextRule;
+    use crate::turtle::Turtle;
+    use crate::ParametricLSystem;
+    use maplit::hashmap;
+
+    #[test]
+    fn matches_evolve() {
+        let mut system = ParametricLSystem::new(
+            vec!['F', 'X'],
+            hashmap! {
+                'X' => "X+YF+".chars().collect(),
+                'Y' => "-FX-Y".chars().collect(),
+            },
+        );
+
+        let expanded = (0..6)
+            .map(|n| system.expand(n).unwrap().cloned().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        for generation in expanded {
+            assert_eq!(generation, system.state);
+            system.step();
+        }
+    }
+
+    #[test]
+    fn feeds_a_turtle() {
+        let system =
+            ParametricLSystem::new(vec!['F'], hashmap! { 'F' => "F+F-F-F+F".chars().collect() });
+
+        let lines = Turtle::standard(90.).draw(system.expand(4).unwrap());
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].len(), 5usize.pow(4) + 1);
+        let (x, y) = *lines[0].last().unwrap();
+        assert!((x - 81.).abs() < 1e-6 && y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn refuses_context() {
+        let system = ParametricLSystem::new(vec!['a', 'b'], hashmap! {}).with_context_rules(vec![
+            ContextRule {
+                left: vec!['a'],
+                predecessor: 'b',
+                right: Vec::new(),
+                successor: vec!['a'],
+            },
+        ]);
+
+        assert_eq!(system.expand(1).err(), Some(ContextSensitive));
+    }
+}
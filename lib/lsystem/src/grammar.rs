@@ -0,0 +1,233 @@
+//! a small text format for L-systems over `char`s, so designs can live in files.
+//!
+//! ```text
+//! # sierpinski arrowhead
+//! axiom: A
+//! angle: 60
+//! iterations: 8
+//! draw: AB
+//! A -> B-A-B
+//! B -> A+B+A
+//! ```
+//!
+//! rules are `predecessor -> successor`, optionally with context: `left < predecessor > right ->
+//! successor`. `ignore: +-` lists symbols skipped over when matching context, and `[`/`]` always
+//! delimit branches. `draw: AB` lists symbols, besides `F`, that the turtle draws with.
+//! whitespace inside axioms and rules is insignificant, and `#` starts a comment.
+
+use crate::context::ContextRule;
+use crate::turtle::{Action, Turtle};
+use crate::ParametricLSystem;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grammar {
+    pub axiom: Vec<char>,
+    pub rules: HashMap<char, Vec<char>>,
+    pub context_rules: Vec<ContextRule<char>>,
+    pub ignore: HashSet<char>,
+    /// turning angle in degrees, for whoever draws the result.
+    pub angle: Option<f64>,
+    /// how many generations the design is meant to be evolved for.
+    pub iterations: Option<usize>,
+    /// symbols that draw a line, on top of the standard turtle's.
+    pub draw: Vec<char>,
+}
+
+impl Grammar {
+    pub fn system(&self) -> ParametricLSystem<char> {
+        ParametricLSystem::new(self.axiom.clone(), self.rules.clone())
+            .with_context_rules(self.context_rules.clone())
+            .ignoring(self.ignore.iter().cloned())
+            .with_brackets('[', ']')
+    }
+
+    /// a standard turtle, turning by `angle` (or 90°) and drawing with `draw` as well as `F`.
+    pub fn turtle(&self) -> Turtle<char> {
+        self.draw.iter().fold(
+            Turtle::standard(self.angle.unwrap_or(90.)),
+            |turtle, &symbol| turtle.map(symbol, Action::Draw),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+fn symbols(s: &str) -> Vec<char> {
+    s.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+fn single(s: &str) -> Result<char, String> {
+    match symbols(s).as_slice() {
+        [c] => Ok(*c),
+        [] => Err("missing predecessor".to_string()),
+        _ => Err(format!(
+            "predecessor `{}` should be a single symbol",
+            s.trim()
+        )),
+    }
+}
+
+impl FromStr for Grammar {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut axiom = None;
+        let mut grammar = Grammar {
+            axiom: Vec::new(),
+            rules: HashMap::new(),
+            context_rules: Vec::new(),
+            ignore: HashSet::new(),
+            angle: None,
+            iterations: None,
+            draw: Vec::new(),
+        };
+
+        for (i, line) in s.lines().enumerate() {
+            let error = |message: String| ParseError {
+                line: i + 1,
+                message,
+            };
+
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(arrow) = line.find("->") {
+                let (lhs, successor) = (&line[..arrow], symbols(&line[arrow + 2..]));
+                let (left, lhs) = match lhs.find('<') {
+                    Some(lt) => (symbols(&lhs[..lt]), &lhs[lt + 1..]),
+                    None => (Vec::new(), lhs),
+                };
+                let (lhs, right) = match lhs.find('>') {
+                    Some(gt) => (&lhs[..gt], symbols(&lhs[gt + 1..])),
+                    None => (lhs, Vec::new()),
+                };
+                let predecessor = single(lhs).map_err(error)?;
+
+                if left.is_empty() && right.is_empty() {
+                    if grammar.rules.insert(predecessor, successor).is_some() {
+                        return Err(error(format!("`{}` already has a rule", predecessor)));
+                    }
+                } else {
+                    grammar.context_rules.push(ContextRule {
+                        left,
+                        predecessor,
+                        right,
+                        successor,
+                    });
+                }
+            } else if let Some(colon) = line.find(':') {
+                let (key, value) = (line[..colon].trim(), line[colon + 1..].trim());
+                match key {
+                    "axiom" => axiom = Some(symbols(value)),
+                    "ignore" => grammar.ignore.extend(symbols(value)),
+                    "draw" => grammar.draw.extend(symbols(value)),
+                    "angle" => {
+                        let angle = value
+                            .parse()
+                            .map_err(|_| error(format!("bad angle `{}`", value)))?;
+                        grammar.angle = Some(angle);
+                    }
+                    "iterations" => {
+                        let iterations = value
+                            .parse()
+                            .map_err(|_| error(format!("bad iteration count `{}`", value)))?;
+                        grammar.iterations = Some(iterations);
+                    }
+                    _ => return Err(error(format!("unknown setting `{}`", key))),
+                }
+            } else {
+                return Err(error("expected `setting: value` or a rule".to_string()));
+            }
+        }
+
+        grammar.axiom = axiom.ok_or(ParseError {
+            line: s.lines().count(),
+            message: "no axiom".to_string(),
+        })?;
+
+        Ok(grammar)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arrowhead() {
+        let grammar: Grammar = "
+            # sierpinski arrowhead
+            axiom: A
+            angle: 60
+            iterations: 8
+            draw: AB
+            A -> B-A-B
+            B -> A + B + A  # spaces are fine
+        "
+        .parse()
+        .unwrap();
+
+        assert_eq!(grammar.angle, Some(60.));
+        assert_eq!(grammar.iterations, Some(8));
+
+        let mut system = grammar.system();
+        system.step();
+        system.step();
+        assert_eq!(system.state.iter().collect::<String>(), "A+B+A-B-A-B-A+B+A");
+        assert_eq!(grammar.turtle().draw(&system.state)[0].len(), 10);
+    }
+
+    #[test]
+    fn context_rules() {
+        let grammar: Grammar = "
+            axiom: b a + a
+            ignore: +
+            b < a -> b
+            b -> a
+        "
+        .parse()
+        .unwrap();
+
+        let mut system = grammar.system();
+        system.step();
+        assert_eq!(system.state, vec!['a', 'b', '+', 'a']);
+        system.step();
+        assert_eq!(system.state, vec!['a', 'a', '+', 'b']);
+    }
+
+    #[test]
+    fn errors() {
+        let line = |s: &str| s.parse::<Grammar>().unwrap_err().line;
+
+        assert_eq!(line("A -> AB"), 1);
+        assert_eq!(line("axiom: A\nAB -> A"), 2);
+        assert_eq!(line("axiom: A\n -> A"), 2);
+        assert_eq!(line("axiom: A\nA -> B\nA -> C"), 3);
+        assert_eq!(line("axiom: A\nangle: steep"), 2);
+        assert_eq!(line("axiom: A\ncolour: red"), 2);
+        assert_eq!(line("axiom: A\nA = B"), 2);
+    }
+}
@@ -2,9 +2,24 @@
 //! probably not particularly well-optimized, but made with <3
 
 use core::hash::Hash;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-// currently, can only represent non-stochastic, context-free L-systems
+pub mod branch;
+pub mod context;
+pub mod expand;
+pub mod grammar;
+pub mod parametric;
+pub mod presets;
+pub mod stochastic;
+pub mod turtle;
+pub mod turtle3d;
+
+use branch::{BracketError, Node};
+use context::{ContextRule, Neighbourhood};
+
+// currently, can only represent non-stochastic L-systems over plain symbols.
+// see `stochastic` for weighted alternatives, and `parametric` for symbols with parameters.
 type ProductionRules<Symbol> = HashMap<Symbol, Vec<Symbol>>;
 
 pub struct ParametricLSystem<Symbol>
@@ -12,6 +27,12 @@ where
     Symbol: Eq + Hash + Clone,
 {
     pub rules: ProductionRules<Symbol>,
+    /// tried in order before falling back to `rules`; the first match wins.
+    pub context_rules: Vec<ContextRule<Symbol>>,
+    /// symbols skipped over when matching context, e.g. turtle turns.
+    pub ignore: HashSet<Symbol>,
+    /// `(push, pop)` symbols delimiting branches, if context matching should follow them.
+    pub brackets: Option<(Symbol, Symbol)>,
     pub state: Vec<Symbol>,
 }
 
@@ -22,14 +43,167 @@ where
     pub fn new(axiom: Vec<Symbol>, rules: ProductionRules<Symbol>) -> Self {
         Self {
             rules,
+            context_rules: Vec::new(),
+            ignore: HashSet::new(),
+            brackets: None,
             state: axiom,
         }
     }
 
+    pub fn with_context_rules(mut self, context_rules: Vec<ContextRule<Symbol>>) -> Self {
+        self.context_rules = context_rules;
+        self
+    }
+
+    pub fn ignoring<I>(mut self, symbols: I) -> Self
+    where
+        I: IntoIterator<Item = Symbol>,
+    {
+        self.ignore.extend(symbols);
+        self
+    }
+
+    pub fn with_brackets(mut self, push: Symbol, pop: Symbol) -> Self {
+        self.brackets = Some((push, pop));
+        self
+    }
+
     pub fn evolve(&self) -> Vec<Symbol> {
-        let mut expanded = Vec::with_capacity(self.state.len());
-        for symbol in self.state.iter() {
-            if let Some(replacement) = self.rules.get(symbol) {
+        self.rewrite(&self.state)
+    }
+
+    /// evolves the system by one generation, in place.
+    pub fn step(&mut self) {
+        self.state = self.evolve();
+    }
+
+    pub fn step_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+
+    /// the current state and every generation after it, leaving the system itself untouched.
+    pub fn generations(&self) -> Generations<'_, Symbol> {
+        Generations {
+            system: self,
+            next: Some(Generation {
+                number: 0,
+                state: self.state.clone(),
+            }),
+        }
+    }
+
+    /// the production for `symbol`, ignoring context.
+    pub fn rule(&self, symbol: &Symbol) -> Option<&[Symbol]> {
+        self.rules.get(symbol).map(Vec::as_slice)
+    }
+
+    /// every symbol in the state or any rule.
+    pub fn alphabet(&self) -> HashSet<Symbol> {
+        let mut alphabet: HashSet<Symbol> = self.state.iter().cloned().collect();
+        for (predecessor, successor) in &self.rules {
+            alphabet.insert(predecessor.clone());
+            alphabet.extend(successor.iter().cloned());
+        }
+        for rule in &self.context_rules {
+            alphabet.insert(rule.predecessor.clone());
+            alphabet.extend(rule.left.iter().cloned());
+            alphabet.extend(rule.right.iter().cloned());
+            alphabet.extend(rule.successor.iter().cloned());
+        }
+
+        alphabet
+    }
+
+    /// symbols that can ever show up, starting from the current state.
+    fn reachable(&self) -> HashSet<Symbol> {
+        let mut reachable: HashSet<Symbol> = HashSet::new();
+        let mut frontier: Vec<Symbol> = self.state.clone();
+        while let Some(symbol) = frontier.pop() {
+            if !reachable.insert(symbol.clone()) {
+                continue;
+            }
+
+            let successors = self.rules.get(&symbol).into_iter().chain(
+                self.context_rules
+                    .iter()
+                    .filter(|rule| rule.predecessor == symbol)
+                    .map(|rule| &rule.successor),
+            );
+            for successor in successors {
+                frontier.extend(successor.iter().cloned());
+            }
+        }
+
+        reachable
+    }
+
+    /// things about the rules that are probably mistakes.
+    pub fn validate(&self) -> Vec<Warning<Symbol>> {
+        let reachable = self.reachable();
+
+        let mut warnings = Vec::new();
+        let predecessors = self
+            .rules
+            .keys()
+            .chain(self.context_rules.iter().map(|rule| &rule.predecessor));
+        for predecessor in predecessors {
+            let warning = Warning::Unreachable(predecessor.clone());
+            if !reachable.contains(predecessor) && !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+
+        if let Some((push, pop)) = &self.brackets {
+            if let Err(error) = branch::partners(&self.state, push, pop) {
+                warnings.push(Warning::UnbalancedState(error));
+            }
+
+            let productions = self.rules.iter().chain(
+                self.context_rules
+                    .iter()
+                    .map(|rule| (&rule.predecessor, &rule.successor)),
+            );
+            for (predecessor, successor) in productions {
+                let warning = Warning::UnbalancedRule(predecessor.clone());
+                if branch::partners(successor, push, pop).is_err() && !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// checks the current state's brackets pair up; trivially true without brackets.
+    pub fn check_brackets(&self) -> Result<(), BracketError> {
+        match &self.brackets {
+            Some((push, pop)) => branch::partners(&self.state, push, pop).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// the current state with branches nested, or flat without brackets.
+    pub fn tree(&self) -> Result<Vec<Node<Symbol>>, BracketError> {
+        match &self.brackets {
+            Some((push, pop)) => branch::tree(&self.state, push, pop),
+            None => Ok(self.state.iter().cloned().map(Node::Symbol).collect()),
+        }
+    }
+
+    fn rewrite(&self, state: &[Symbol]) -> Vec<Symbol> {
+        let neighbourhood = Neighbourhood::new(state, &self.ignore, self.brackets.as_ref());
+
+        let mut expanded = Vec::with_capacity(state.len());
+        for (i, symbol) in state.iter().enumerate() {
+            if let Some(rule) = self
+                .context_rules
+                .iter()
+                .find(|rule| rule.matches(&neighbourhood, i))
+            {
+                expanded.extend(rule.successor.iter().cloned());
+            } else if let Some(replacement) = self.rules.get(symbol) {
                 expanded.extend(replacement.iter().cloned());
             } else {
                 expanded.push(symbol.clone());
@@ -40,6 +214,90 @@ where
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct Generation<Symbol> {
+    /// how many times the starting state has been evolved.
+    pub number: usize,
+    pub state: Vec<Symbol>,
+}
+
+impl<Symbol> Generation<Symbol>
+where
+    Symbol: Eq + Hash + Clone,
+{
+    pub fn len(&self) -> usize {
+        self.state.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.is_empty()
+    }
+
+    /// how many of each symbol there are.
+    pub fn counts(&self) -> HashMap<Symbol, usize> {
+        let mut counts = HashMap::new();
+        for symbol in &self.state {
+            *counts.entry(symbol.clone()).or_insert(0) += 1;
+        }
+
+        counts
+    }
+}
+
+pub struct Generations<'a, Symbol>
+where
+    Symbol: Eq + Hash + Clone,
+{
+    system: &'a ParametricLSystem<Symbol>,
+    next: Option<Generation<Symbol>>,
+}
+
+impl<'a, Symbol> Iterator for Generations<'a, Symbol>
+where
+    Symbol: Eq + Hash + Clone,
+{
+    type Item = Generation<Symbol>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        self.next = Some(Generation {
+            number: current.number + 1,
+            state: self.system.rewrite(&current.state),
+        });
+
+        Some(current)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning<Symbol> {
+    /// there's a rule for this symbol, but it never turns up in the state.
+    Unreachable(Symbol),
+    /// the state's brackets don't pair up.
+    UnbalancedState(BracketError),
+    /// this symbol's production opens a branch without closing it, or vice versa.
+    UnbalancedRule(Symbol),
+}
+
+impl<Symbol> fmt::Display for Warning<Symbol>
+where
+    Symbol: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::Unreachable(symbol) => write!(
+                f,
+                "the rule for {:?} never applies: it isn't in the axiom or any production",
+                symbol
+            ),
+            Warning::UnbalancedState(error) => write!(f, "unbalanced brackets {}", error),
+            Warning::UnbalancedRule(symbol) => {
+                write!(f, "the rule for {:?} has unbalanced brackets", symbol)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -74,4 +332,122 @@ mod test {
         system.state = system.evolve();
         assert_eq!(system.state, &[A, B, A, A, B, A, B, A, A, B, A, A, B]);
     }
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    fn rule(left: &str, predecessor: char, right: &str, successor: &str) -> ContextRule<char> {
+        ContextRule {
+            left: chars(left),
+            predecessor,
+            right: chars(right),
+            successor: chars(successor),
+        }
+    }
+
+    #[test]
+    fn signal_propagation() {
+        // ABOP, p. 30
+        let mut system = ParametricLSystem::new(chars("baaaa"), hashmap! { 'b' => vec!['a'] })
+            .with_context_rules(vec![rule("b", 'a', "", "b")]);
+
+        system.step();
+        assert_eq!(system.state, chars("abaaa"));
+        system.step();
+        assert_eq!(system.state, chars("aabaa"));
+    }
+
+    #[test]
+    fn context_skips_ignored_symbols() {
+        let system = ParametricLSystem::new(chars("A+-B"), hashmap! {})
+            .with_context_rules(vec![rule("A", 'B', "", "X"), rule("", 'A', "B", "Y")])
+            .ignoring(chars("+-"));
+
+        assert_eq!(system.evolve(), chars("Y+-X"));
+    }
+
+    #[test]
+    fn context_follows_branches() {
+        let system = ParametricLSystem::new(chars("A[+B]C[D]E"), hashmap! {})
+            .with_context_rules(vec![
+                // a branch's first symbol sees what precedes the branch
+                rule("A", 'B', "", "b"),
+                // sibling branches are skipped, looking left or right
+                rule("B", 'E', "", "!"),
+                rule("C", 'E', "", "e"),
+                rule("A", 'C', "E", "x"),
+                // right context can be found down a branch
+                rule("", 'A', "B", "a"),
+            ])
+            .ignoring(vec!['+'])
+            .with_brackets('[', ']');
+
+        assert_eq!(system.evolve(), chars("a[+b]x[D]e"));
+    }
+
+    #[test]
+    fn stepping() {
+        let rules = hashmap! { 'A' => vec!['A', 'B'], 'B' => vec!['A'] };
+        let mut system = ParametricLSystem::new(vec!['A'], rules);
+
+        let lengths = system
+            .generations()
+            .take(6)
+            .map(|generation| generation.len())
+            .collect::<Vec<_>>();
+        assert_eq!(lengths, &[1, 2, 3, 5, 8, 13]);
+        assert_eq!(system.state, &['A']);
+
+        system.step();
+        assert_eq!(system.state, &['A', 'B']);
+        system.step_n(4);
+        assert_eq!(system.state.len(), 13);
+
+        let next = system.generations().nth(1).unwrap();
+        assert_eq!(next.number, 1);
+        assert_eq!(next.counts(), hashmap! { 'A' => 13, 'B' => 8 });
+    }
+
+    #[test]
+    fn unreachable_rules() {
+        let system = ParametricLSystem::new(
+            chars("A"),
+            hashmap! { 'A' => chars("AB"), 'B' => chars("A"), 'C' => chars("CC") },
+        )
+        .with_context_rules(vec![rule("A", 'D', "", "A"), rule("A", 'B', "", "C")]);
+
+        assert_eq!(system.validate(), &[Warning::Unreachable('D')]);
+        assert_eq!(
+            ParametricLSystem::new(chars("A"), hashmap! { 'B' => chars("A") }).validate(),
+            &[Warning::Unreachable('B')]
+        );
+    }
+
+    #[test]
+    fn branching() {
+        let system = ParametricLSystem::new(chars("F[+F]"), hashmap! { 'F' => chars("F[F") })
+            .with_brackets('[', ']');
+
+        assert_eq!(system.check_brackets(), Ok(()));
+        assert_eq!(
+            system.tree(),
+            Ok(vec![
+                Node::Symbol('F'),
+                Node::Branch(vec![Node::Symbol('+'), Node::Symbol('F')]),
+            ])
+        );
+        assert_eq!(system.validate(), &[Warning::UnbalancedRule('F')]);
+
+        let mut system = system;
+        system.step();
+        assert_eq!(system.check_brackets(), Err(BracketError::Unclosed(3)));
+        assert_eq!(
+            system.validate(),
+            &[
+                Warning::UnbalancedState(BracketError::Unclosed(3)),
+                Warning::UnbalancedRule('F')
+            ]
+        );
+    }
 }
@@ -0,0 +1,606 @@
+//! parametric L-systems, where symbols carry numeric parameters which rules can compute with
+//! and test against. e.g. with
+//!
+//! ```text
+//! F(l) : l > 2 -> F(l/2) + F(l/2)
+//! ```
+//!
+//! `F(8)` halves into `F(4) + F(4)`, then `F(2) + F(2) + F(2) + F(2)`, then stops growing.
+
+use core::hash::Hash;
+use std::error::Error;
+use std::fmt;
+
+/// a symbol together with its actual parameters, e.g. `F(2.5)`. plain symbols like `+` are
+/// modules with no parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Module<Symbol> {
+    pub symbol: Symbol,
+    pub params: Vec<f64>,
+}
+
+impl<Symbol> Module<Symbol> {
+    pub fn new(symbol: Symbol, params: Vec<f64>) -> Self {
+        Self { symbol, params }
+    }
+
+    pub fn plain(symbol: Symbol) -> Self {
+        Self {
+            symbol,
+            params: Vec::new(),
+        }
+    }
+}
+
+impl<Symbol: fmt::Display> fmt::Display for Module<Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol)?;
+        if !self.params.is_empty() {
+            let params: Vec<String> = self.params.iter().map(|p| p.to_string()).collect();
+            write!(f, "({})", params.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// arithmetic over a rule's formal parameters, which are referred to by position.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Const(f64),
+    Param(usize),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Pow(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn eval(&self, params: &[f64]) -> f64 {
+        use Expr::*;
+        match self {
+            Const(value) => *value,
+            Param(i) => params[*i],
+            Neg(e) => -e.eval(params),
+            Add(a, b) => a.eval(params) + b.eval(params),
+            Sub(a, b) => a.eval(params) - b.eval(params),
+            Mul(a, b) => a.eval(params) * b.eval(params),
+            Div(a, b) => a.eval(params) / b.eval(params),
+            Pow(a, b) => a.eval(params).powf(b.eval(params)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+/// guard deciding whether a rule applies to a module's parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    Compare(Expr, Comparison, Expr),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    pub fn holds(&self, params: &[f64]) -> bool {
+        match self {
+            Condition::Compare(a, cmp, b) => {
+                let (a, b) = (a.eval(params), b.eval(params));
+                match cmp {
+                    Comparison::Lt => a < b,
+                    Comparison::Le => a <= b,
+                    Comparison::Gt => a > b,
+                    Comparison::Ge => a >= b,
+                    Comparison::Eq => (a - b).abs() < f64::EPSILON,
+                    Comparison::Ne => (a - b).abs() >= f64::EPSILON,
+                }
+            }
+            Condition::And(a, b) => a.holds(params) && b.holds(params),
+            Condition::Or(a, b) => a.holds(params) || b.holds(params),
+            Condition::Not(c) => !c.holds(params),
+        }
+    }
+}
+
+/// `predecessor(formals) : condition -> successor`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule<Symbol> {
+    pub predecessor: Symbol,
+    /// number of formal parameters; a rule only matches modules with exactly this many.
+    pub arity: usize,
+    pub condition: Option<Condition>,
+    pub successor: Vec<(Symbol, Vec<Expr>)>,
+}
+
+impl<Symbol> Rule<Symbol>
+where
+    Symbol: Eq + Clone,
+{
+    pub fn matches(&self, module: &Module<Symbol>) -> bool {
+        self.predecessor == module.symbol
+            && self.arity == module.params.len()
+            && match &self.condition {
+                Some(condition) => condition.holds(&module.params),
+                None => true,
+            }
+    }
+
+    pub fn apply(&self, params: &[f64]) -> impl Iterator<Item = Module<Symbol>> + '_ {
+        let params = params.to_vec();
+        self.successor.iter().map(move |(symbol, exprs)| Module {
+            symbol: symbol.clone(),
+            params: exprs.iter().map(|e| e.eval(&params)).collect(),
+        })
+    }
+}
+
+pub struct ModuleLSystem<Symbol>
+where
+    Symbol: Eq + Hash + Clone,
+{
+    /// tried in order; the first matching rule wins.
+    pub rules: Vec<Rule<Symbol>>,
+    pub state: Vec<Module<Symbol>>,
+}
+
+impl<Symbol> ModuleLSystem<Symbol>
+where
+    Symbol: Eq + Hash + Clone,
+{
+    pub fn new(axiom: Vec<Module<Symbol>>, rules: Vec<Rule<Symbol>>) -> Self {
+        Self {
+            rules,
+            state: axiom,
+        }
+    }
+
+    pub fn evolve(&self) -> Vec<Module<Symbol>> {
+        let mut expanded = Vec::with_capacity(self.state.len());
+        for module in self.state.iter() {
+            match self.rules.iter().find(|rule| rule.matches(module)) {
+                Some(rule) => expanded.extend(rule.apply(&module.params)),
+                None => expanded.push(module.clone()),
+            }
+        }
+
+        expanded
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// char offset into the input.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at {}: {}", self.position, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+/// recursive-descent parser over single-`char` symbols.
+struct Parser<'f> {
+    chars: Vec<char>,
+    position: usize,
+    formals: &'f [String],
+}
+
+impl<'f> Parser<'f> {
+    fn new(input: &str, formals: &'f [String]) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            position: 0,
+            formals,
+        }
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            position: self.position,
+            message: message.to_string(),
+        })
+    }
+
+    fn current_is<P>(&self, predicate: P) -> bool
+    where
+        P: Fn(char) -> bool,
+    {
+        match self.chars.get(self.position) {
+            Some(&c) => predicate(c),
+            None => false,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.current_is(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.position).cloned()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let matches = token
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.position + i) == Some(&c));
+        if matches {
+            self.position += token.chars().count();
+        }
+
+        matches
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            self.error(&format!("expected `{}`", token))
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.peek().is_none()
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let start = self.position;
+        while self.current_is(|c| c.is_alphanumeric() || c == '_') {
+            if self.position == start && self.chars[start].is_numeric() {
+                break;
+            }
+            self.position += 1;
+        }
+
+        if self.position > start {
+            Some(self.chars[start..self.position].iter().collect())
+        } else {
+            None
+        }
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        self.skip_whitespace();
+        let start = self.position;
+        while self.current_is(|c| c.is_ascii_digit() || c == '.') {
+            self.position += 1;
+        }
+
+        let text: String = self.chars[start..self.position].iter().collect();
+        match text.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.position = start;
+                None
+            }
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.term()?;
+        loop {
+            if self.eat("+") {
+                lhs = Expr::Add(Box::new(lhs), Box::new(self.term()?));
+            } else if self.eat("-") {
+                lhs = Expr::Sub(Box::new(lhs), Box::new(self.term()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.factor()?;
+        loop {
+            if self.eat("*") {
+                lhs = Expr::Mul(Box::new(lhs), Box::new(self.factor()?));
+            } else if self.eat("/") {
+                lhs = Expr::Div(Box::new(lhs), Box::new(self.factor()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.factor()?)));
+        }
+
+        let base = self.atom()?;
+        if self.eat("^") {
+            // right-associative, and binds tighter than unary minus on its left
+            Ok(Expr::Pow(Box::new(base), Box::new(self.factor()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, ParseError> {
+        if self.eat("(") {
+            let inner = self.expr()?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+        if let Some(value) = self.number() {
+            return Ok(Expr::Const(value));
+        }
+
+        let position = self.position;
+        match self.identifier() {
+            Some(name) => match self.formals.iter().position(|f| *f == name) {
+                Some(i) => Ok(Expr::Param(i)),
+                None => Err(ParseError {
+                    position,
+                    message: format!("unknown parameter `{}`", name),
+                }),
+            },
+            None => self.error("expected an expression"),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Condition, ParseError> {
+        if self.eat("!") {
+            return Ok(Condition::Not(Box::new(self.comparison()?)));
+        }
+
+        let lhs = self.expr()?;
+        let cmp = if self.eat("<=") {
+            Comparison::Le
+        } else if self.eat(">=") {
+            Comparison::Ge
+        } else if self.eat("==") {
+            Comparison::Eq
+        } else if self.eat("!=") {
+            Comparison::Ne
+        } else if self.eat("<") {
+            Comparison::Lt
+        } else if self.eat(">") {
+            Comparison::Gt
+        } else if self.eat("=") {
+            Comparison::Eq
+        } else {
+            return self.error("expected a comparison");
+        };
+
+        Ok(Condition::Compare(lhs, cmp, self.expr()?))
+    }
+
+    fn conjunction(&mut self) -> Result<Condition, ParseError> {
+        let mut lhs = self.comparison()?;
+        while self.eat("&&") {
+            lhs = Condition::And(Box::new(lhs), Box::new(self.comparison()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn condition(&mut self) -> Result<Condition, ParseError> {
+        let mut lhs = self.conjunction()?;
+        while self.eat("||") {
+            lhs = Condition::Or(Box::new(lhs), Box::new(self.conjunction()?));
+        }
+
+        Ok(lhs)
+    }
+
+    /// a string of modules with parameter expressions, e.g. `F(l/2)+F(l/2)`.
+    fn successor(&mut self) -> Result<Vec<(char, Vec<Expr>)>, ParseError> {
+        let mut modules = Vec::new();
+        while let Some(symbol) = self.peek() {
+            self.position += 1;
+
+            let mut params = Vec::new();
+            if self.chars.get(self.position) == Some(&'(') {
+                self.position += 1;
+                loop {
+                    params.push(self.expr()?);
+                    if self.eat(")") {
+                        break;
+                    }
+                    self.expect(",")?;
+                }
+            }
+            modules.push((symbol, params));
+        }
+
+        Ok(modules)
+    }
+}
+
+impl Rule<char> {
+    /// parses `P(a, b) : condition -> successor`, where the condition is optional. symbols are
+    /// single characters and whitespace between modules is ignored.
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let arrow = match source.find("->") {
+            Some(arrow) => arrow,
+            None => {
+                return Err(ParseError {
+                    position: 0,
+                    message: "expected `->`".to_string(),
+                })
+            }
+        };
+        let (lhs, rhs) = (&source[..arrow], &source[arrow + 2..]);
+        let offset = |p: ParseError, base: &str| ParseError {
+            position: p.position + base.chars().count(),
+            ..p
+        };
+
+        let (head, guard) = match lhs.find(':') {
+            Some(colon) => (&lhs[..colon], Some(&lhs[colon + 1..])),
+            None => (lhs, None),
+        };
+
+        // predecessor and its formal parameter names
+        let no_formals: Vec<String> = Vec::new();
+        let mut parser = Parser::new(head, &no_formals);
+        let predecessor = match parser.peek() {
+            Some(symbol) => symbol,
+            None => return parser.error("expected a predecessor"),
+        };
+        parser.position += 1;
+        let mut formals = Vec::new();
+        if parser.eat("(") {
+            loop {
+                match parser.identifier() {
+                    Some(name) => formals.push(name),
+                    None => return parser.error("expected a parameter name"),
+                }
+                if parser.eat(")") {
+                    break;
+                }
+                parser.expect(",")?;
+            }
+        }
+        if !parser.at_end() {
+            return parser.error("unexpected input after predecessor");
+        }
+
+        let condition = match guard {
+            Some(guard) => {
+                let mut parser = Parser::new(guard, &formals);
+                let condition = parser
+                    .condition()
+                    .map_err(|e| offset(e, &source[..=head.len()]))?;
+                if !parser.at_end() {
+                    return Err(offset(
+                        ParseError {
+                            position: parser.position,
+                            message: "unexpected input after condition".to_string(),
+                        },
+                        &source[..=head.len()],
+                    ));
+                }
+                Some(condition)
+            }
+            None => None,
+        };
+
+        let successor = Parser::new(rhs, &formals)
+            .successor()
+            .map_err(|e| offset(e, &source[..arrow + 2]))?;
+
+        Ok(Self {
+            predecessor,
+            arity: formals.len(),
+            condition,
+            successor,
+        })
+    }
+}
+
+impl Module<char> {
+    /// parses a string of modules with constant parameters, e.g. an axiom like `A(1,10)B`.
+    pub fn parse_string(source: &str) -> Result<Vec<Self>, ParseError> {
+        Ok(Parser::new(source, &[])
+            .successor()?
+            .into_iter()
+            .map(|(symbol, exprs)| Module {
+                symbol,
+                params: exprs.iter().map(|e| e.eval(&[])).collect(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn show(state: &[Module<char>]) -> String {
+        state.iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn halving() {
+        let mut system = ModuleLSystem::new(
+            Module::parse_string("F(8)").unwrap(),
+            vec![Rule::parse("F(l) : l > 2 -> F(l/2) + F(l/2)").unwrap()],
+        );
+
+        system.state = system.evolve();
+        assert_eq!(show(&system.state), "F(4)+F(4)");
+        system.state = system.evolve();
+        assert_eq!(show(&system.state), "F(2)+F(2)+F(2)+F(2)");
+        system.state = system.evolve();
+        assert_eq!(show(&system.state), "F(2)+F(2)+F(2)+F(2)");
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        // after ABOP, p. 43
+        let mut system = ModuleLSystem::new(
+            Module::parse_string("B(2)A(4,4)").unwrap(),
+            vec![
+                Rule::parse("A(x,y): y <= 3 -> A(x*2,x+y)").unwrap(),
+                Rule::parse("A(x,y): y > 3 -> B(x)A(x/y,0)").unwrap(),
+                Rule::parse("B(x) : x < 1 -> C").unwrap(),
+                Rule::parse("B(x) : x >= 1 -> B(x-1)").unwrap(),
+            ],
+        );
+
+        system.state = system.evolve();
+        assert_eq!(show(&system.state), "B(1)B(4)A(1,0)");
+        system.state = system.evolve();
+        assert_eq!(show(&system.state), "B(0)B(3)A(2,1)");
+        system.state = system.evolve();
+        assert_eq!(show(&system.state), "CB(2)A(4,3)");
+    }
+
+    #[test]
+    fn expressions() {
+        let formals = vec!["a".to_string(), "b".to_string()];
+        let eval = |source: &str| {
+            Parser::new(source, &formals)
+                .expr()
+                .unwrap()
+                .eval(&[3., 2.])
+        };
+
+        assert_eq!(eval("a + b * 2"), 7.);
+        assert_eq!(eval("(a + b) * 2"), 10.);
+        assert_eq!(eval("-a ^ b"), -9.);
+        assert_eq!(eval("b ^ -1"), 0.5);
+        assert_eq!(eval("2 ^ b ^ b"), 16.);
+        assert_eq!(eval("a / b - 0.5"), 1.);
+
+        let holds = |source: &str| {
+            Parser::new(source, &formals)
+                .condition()
+                .unwrap()
+                .holds(&[3., 2.])
+        };
+        assert!(holds("a > b && b == 2"));
+        assert!(holds("a < b || !a < 1"));
+        assert!(!holds("a != 3"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Rule::parse("F(l) : l > 2 F(l)").is_err());
+        assert!(Rule::parse("F(l) -> F(m)").is_err());
+        assert!(Rule::parse("F(l) : l >> 2 -> F(l)").is_err());
+        assert!(Module::parse_string("F(1,").is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! a catalog of well-known L-systems, written in the `grammar` format.
+//!
+//! ```
+//! let grammar = lsystem::presets::find("dragon").unwrap().grammar();
+//! let mut system = grammar.system();
+//! system.step_n(10);
+//! let lines = grammar.turtle().draw(&system.state);
+//! ```
+
+use crate::grammar::Grammar;
+
+pub struct Preset {
+    pub name: &'static str,
+    pub source: &'static str,
+}
+
+impl Preset {
+    pub fn grammar(&self) -> Grammar {
+        self.source
+            .parse()
+            .unwrap_or_else(|e| panic!("preset {} doesn't parse: {}", self.name, e))
+    }
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "koch-snowflake",
+        source: "
+            axiom: F--F--F
+            angle: 60
+            iterations: 4
+            F -> F+F--F+F
+        ",
+    },
+    Preset {
+        // ABOP, fig. 1.7b
+        name: "quadratic-koch",
+        source: "
+            axiom: F-F-F-F
+            angle: 90
+            iterations: 3
+            F -> F-F+F+FF-F-F+F
+        ",
+    },
+    Preset {
+        name: "dragon",
+        source: "
+            axiom: FX
+            angle: 90
+            iterations: 12
+            X -> X+YF+
+            Y -> -FX-Y
+        ",
+    },
+    Preset {
+        name: "hilbert",
+        source: "
+            axiom: A
+            angle: 90
+            iterations: 6
+            A -> +BF-AFA-FB+
+            B -> -AF+BFB+FA-
+        ",
+    },
+    Preset {
+        // ABOP, fig. 1.11a
+        name: "gosper",
+        source: "
+            axiom: A
+            angle: 60
+            iterations: 4
+            draw: AB
+            A -> A-B--B+A++AA+B-
+            B -> +A-BB--B-A++A+B
+        ",
+    },
+    Preset {
+        name: "peano",
+        source: "
+            axiom: X
+            angle: 90
+            iterations: 3
+            X -> XFYFX+F+YFXFY-F-XFYFX
+            Y -> YFXFY-F-XFYFX+F+YFXFY
+        ",
+    },
+    Preset {
+        name: "sierpinski-arrowhead",
+        source: "
+            axiom: A
+            angle: 60
+            iterations: 8
+            draw: AB
+            A -> B-A-B
+            B -> A+B+A
+        ",
+    },
+    Preset {
+        name: "sierpinski-triangle",
+        source: "
+            axiom: F-G-G
+            angle: 120
+            iterations: 6
+            draw: G
+            F -> F-G+F+G-F
+            G -> GG
+        ",
+    },
+    Preset {
+        // penrose's P3 (rhombus) tiling
+        name: "penrose",
+        source: "
+            axiom: [N]++[N]++[N]++[N]++[N]
+            angle: 36
+            iterations: 4
+            M -> OF++PF----NF[-OF----MF]++
+            N -> +OF--PF[---MF--NF]+
+            O -> -MF++NF[+++OF++PF]-
+            P -> --OF++++MF[+PF++++NF]--NF
+            F ->
+        ",
+    },
+    Preset {
+        // ABOP, fig. 1.24a
+        name: "plant-edge",
+        source: "
+            axiom: F
+            angle: 25.7
+            iterations: 5
+            F -> F[+F]F[-F]F
+        ",
+    },
+    Preset {
+        // ABOP, fig. 1.24f
+        name: "plant",
+        source: "
+            axiom: X
+            angle: 25
+            iterations: 6
+            X -> F+[[X]-X]-F[-FX]+X
+            F -> FF
+        ",
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// how many line segments the turtle draws, and how long the string is, for each generation.
+    fn sizes(name: &str, generations: usize) -> Vec<(usize, usize)> {
+        let grammar = find(name).unwrap().grammar();
+        let turtle = grammar.turtle();
+
+        grammar
+            .system()
+            .generations()
+            .take(generations)
+            .map(|generation| {
+                let segments = turtle
+                    .draw(&generation.state)
+                    .iter()
+                    .map(|line| line.len() - 1)
+                    .sum();
+                (segments, generation.len())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn all_valid() {
+        for preset in PRESETS {
+            let grammar = preset.grammar();
+            assert!(grammar.system().validate().is_empty(), "{}", preset.name);
+            assert!(grammar.iterations.is_some(), "{}", preset.name);
+        }
+    }
+
+    #[test]
+    fn segment_counts() {
+        let segments = |name| {
+            sizes(name, 5)
+                .into_iter()
+                .map(|(segments, _)| segments)
+                .collect::<Vec<_>>()
+        };
+        let powers = |base: usize, k: usize, offset: usize| {
+            (0..5).map(|n| k * base.pow(n) - offset).collect::<Vec<_>>()
+        };
+
+        assert_eq!(segments("koch-snowflake"), powers(4, 3, 0));
+        assert_eq!(segments("quadratic-koch"), powers(8, 4, 0));
+        assert_eq!(segments("dragon"), powers(2, 1, 0));
+        assert_eq!(segments("hilbert"), powers(4, 1, 1));
+        assert_eq!(segments("gosper"), powers(7, 1, 0));
+        assert_eq!(segments("peano"), powers(9, 1, 1));
+        assert_eq!(segments("sierpinski-arrowhead"), powers(3, 1, 0));
+        assert_eq!(segments("plant-edge"), powers(5, 1, 0));
+    }
+
+    #[test]
+    fn string_lengths() {
+        let lengths = |name| {
+            sizes(name, 5)
+                .into_iter()
+                .map(|(_, length)| length)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(lengths("dragon"), &[2, 6, 14, 30, 62]);
+        assert_eq!(lengths("hilbert"), &[1, 11, 51, 211, 851]);
+        assert_eq!(lengths("sierpinski-triangle"), &[5, 15, 45, 135, 405]);
+        assert_eq!(lengths("penrose"), &[23, 113, 513, 2283, 10163]);
+        assert_eq!(lengths("plant"), &[1, 18, 89, 379, 1551]);
+    }
+}
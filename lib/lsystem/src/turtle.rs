@@ -0,0 +1,269 @@
+//! turtle graphics, for turning an L-system's state into lines.
+//!
+//! each symbol is looked up in the turtle's mapping; symbols it doesn't know are skipped. the
+//! turtle starts at the origin heading along +x, and `+` turns counter-clockwise.
+
+use core::hash::Hash;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+
+pub type Polyline = Vec<(f64, f64)>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// step forward, drawing.
+    Draw,
+    /// step forward without drawing.
+    Move,
+    /// turn counter-clockwise by the turtle's angle.
+    Left,
+    /// turn clockwise by the turtle's angle.
+    Right,
+    TurnAround,
+    /// remember position, heading and step length, starting a branch.
+    Push,
+    /// go back to where the matching `Push` was, ending the branch.
+    Pop,
+    /// multiply the step length.
+    Scale(f64),
+}
+
+#[derive(Clone, Debug)]
+pub struct Turtle<Symbol>
+where
+    Symbol: Eq + Hash,
+{
+    pub actions: HashMap<Symbol, Action>,
+    /// in radians.
+    pub angle: f64,
+    pub step: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct State {
+    position: (f64, f64),
+    heading: f64,
+    step: f64,
+}
+
+impl<Symbol> Turtle<Symbol>
+where
+    Symbol: Eq + Hash,
+{
+    /// a turtle that doesn't know any symbols yet; `angle` in degrees.
+    pub fn new(angle: f64) -> Self {
+        Self {
+            actions: HashMap::new(),
+            angle: angle.to_radians(),
+            step: 1.,
+        }
+    }
+
+    pub fn map(mut self, symbol: Symbol, action: Action) -> Self {
+        self.actions.insert(symbol, action);
+        self
+    }
+
+    pub fn step(mut self, step: f64) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// walks `symbols`, returning the lines drawn. moving, or popping back out of a branch,
+    /// lifts the pen and starts a new line.
+    pub fn draw<I>(&self, symbols: I) -> Vec<Polyline>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Symbol>,
+    {
+        let mut state = State {
+            position: (0., 0.),
+            heading: 0.,
+            step: self.step,
+        };
+        let mut stack = Vec::new();
+        let mut lines = Vec::new();
+        let mut line = vec![state.position];
+
+        fn lift(line: &mut Polyline, lines: &mut Vec<Polyline>, at: (f64, f64)) {
+            let finished = std::mem::replace(line, vec![at]);
+            if finished.len() > 1 {
+                lines.push(finished);
+            }
+        }
+
+        for symbol in symbols {
+            let action = match self.actions.get(symbol.borrow()) {
+                Some(&action) => action,
+                None => continue,
+            };
+
+            match action {
+                Action::Draw | Action::Move => {
+                    let (x, y) = state.position;
+                    state.position = (
+                        x + state.step * state.heading.cos(),
+                        y + state.step * state.heading.sin(),
+                    );
+                    if action == Action::Draw {
+                        line.push(state.position);
+                    } else {
+                        lift(&mut line, &mut lines, state.position);
+                    }
+                }
+                Action::Left => state.heading += self.angle,
+                Action::Right => state.heading -= self.angle,
+                Action::TurnAround => state.heading += std::f64::consts::PI,
+                Action::Push => stack.push(state),
+                Action::Pop => {
+                    if let Some(saved) = stack.pop() {
+                        state = saved;
+                        lift(&mut line, &mut lines, state.position);
+                    }
+                }
+                Action::Scale(factor) => state.step *= factor,
+            }
+        }
+        lift(&mut line, &mut lines, state.position);
+
+        lines
+    }
+}
+
+impl Turtle<char> {
+    /// the usual symbols: `F` draws, `f` moves, `+`/`-` turn left/right, `|` turns around, and
+    /// `[`/`]` branch. `angle` in degrees.
+    pub fn standard(angle: f64) -> Self {
+        Self::new(angle)
+            .map('F', Action::Draw)
+            .map('f', Action::Move)
+            .map('+', Action::Left)
+            .map('-', Action::Right)
+            .map('|', Action::TurnAround)
+            .map('[', Action::Push)
+            .map(']', Action::Pop)
+    }
+}
+
+/// the smallest rectangle, `(min, max)`, holding every point in `lines`.
+pub fn bounds(lines: &[Polyline]) -> Option<((f64, f64), (f64, f64))> {
+    let mut points = lines.iter().flatten();
+    let &first = points.next()?;
+
+    Some(points.fold((first, first), |(min, max), &(x, y)| {
+        ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+    }))
+}
+
+/// scales and moves `lines` to fill as much of the rectangle from `min` to `max` as they can
+/// without stretching, centred. a single point ends up in the middle.
+pub fn fit(lines: &[Polyline], min: (f64, f64), max: (f64, f64)) -> Vec<Polyline> {
+    let (from, to) = match bounds(lines) {
+        Some(bounds) => bounds,
+        None => return Vec::new(),
+    };
+
+    let (width, height) = (to.0 - from.0, to.1 - from.1);
+    let scales = [(max.0 - min.0) / width, (max.1 - min.1) / height];
+    let scale = scales
+        .iter()
+        .cloned()
+        .filter(|scale| scale.is_finite())
+        .fold(None, |smallest: Option<f64>, scale| match smallest {
+            Some(smallest) => Some(smallest.min(scale)),
+            None => Some(scale),
+        })
+        .unwrap_or(0.);
+
+    let centre = ((from.0 + to.0) / 2., (from.1 + to.1) / 2.);
+    let page_centre = ((min.0 + max.0) / 2., (min.1 + max.1) / 2.);
+    lines
+        .iter()
+        .map(|line| {
+            line.iter()
+                .map(|&(x, y)| {
+                    (
+                        page_centre.0 + (x - centre.0) * scale,
+                        page_centre.1 + (y - centre.1) * scale,
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rounded(lines: Vec<Polyline>) -> Vec<Vec<(i64, i64)>> {
+        lines
+            .into_iter()
+            .map(|line| {
+                line.into_iter()
+                    .map(|(x, y)| (x.round() as i64, y.round() as i64))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn square() {
+        let lines = Turtle::standard(90.).draw("F+F+F+F".chars());
+        assert_eq!(
+            rounded(lines),
+            vec![vec![(0, 0), (1, 0), (1, 1), (0, 1), (0, 0)]]
+        );
+    }
+
+    #[test]
+    fn moves_and_branches_split_lines() {
+        let lines = Turtle::standard(90.).step(2.).draw("FfF[+F]F|FF".chars());
+        assert_eq!(
+            rounded(lines),
+            vec![
+                vec![(0, 0), (2, 0)],
+                vec![(4, 0), (6, 0), (6, 2)],
+                vec![(6, 0), (8, 0), (6, 0), (4, 0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn scaling_is_scoped_to_branches() {
+        let turtle = Turtle::standard(90.)
+            .step(4.)
+            .map('>', Action::Scale(0.5))
+            .map('A', Action::Draw);
+        let lines = turtle.draw("[>>+A]AxA".chars());
+        assert_eq!(
+            rounded(lines),
+            vec![vec![(0, 0), (0, 1)], vec![(0, 0), (4, 0), (8, 0)]]
+        );
+    }
+
+    #[test]
+    fn fits_the_page() {
+        // a curve ending back at x = 0, which used to divide by zero
+        let lines = vec![vec![(0., 0.), (1., 0.), (1., 1.), (0., 1.)]];
+        assert_eq!(bounds(&lines), Some(((0., 0.), (1., 1.))));
+
+        let fitted = rounded(fit(&lines, (0., 0.), (400., 200.)));
+        assert_eq!(
+            fitted,
+            vec![vec![(100, 0), (300, 0), (300, 200), (100, 200)]]
+        );
+
+        // a vertical line only constrains one way
+        let lines = vec![vec![(5., -1.), (5., 1.)]];
+        assert_eq!(
+            rounded(fit(&lines, (0., 0.), (400., 200.))),
+            vec![vec![(200, 0), (200, 200)]]
+        );
+
+        assert_eq!(
+            rounded(fit(&[vec![(3., 4.)]], (0., 0.), (10., 10.))),
+            vec![vec![(5, 5)]]
+        );
+    }
+}
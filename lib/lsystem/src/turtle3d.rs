@@ -0,0 +1,493 @@
+//! a turtle in three dimensions, and a camera to flatten what it draws into plottable lines.
+//!
+//! the turtle starts at the origin heading up (+y), with its left along -x and its up along +z,
+//! so seen from the default camera (looking down -z) `+` and `-` turn it just like the 2D turtle.
+//! rotations follow ABOP, ch. 1.5: `+`/`-` yaw, `&`/`^` pitch down/up, `\`/`/` roll left/right.
+
+use crate::turtle::Polyline;
+use core::hash::Hash;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+
+pub type Point3 = (f64, f64, f64);
+pub type Polyline3 = Vec<Point3>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Draw,
+    Move,
+    YawLeft,
+    YawRight,
+    PitchDown,
+    PitchUp,
+    RollLeft,
+    RollRight,
+    TurnAround,
+    Push,
+    Pop,
+    /// multiply the step length.
+    Scale(f64),
+}
+
+#[derive(Clone, Debug)]
+pub struct Turtle3<Symbol>
+where
+    Symbol: Eq + Hash,
+{
+    pub actions: HashMap<Symbol, Action>,
+    /// in radians.
+    pub angle: f64,
+    pub step: f64,
+}
+
+fn add(a: Point3, b: Point3) -> Point3 {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn scale(a: Point3, k: f64) -> Point3 {
+    (a.0 * k, a.1 * k, a.2 * k)
+}
+
+/// rotates the pair of axes `(a, b)` by `angle`, from `a` towards `b`.
+fn rotate(a: Point3, b: Point3, angle: f64) -> (Point3, Point3) {
+    let (sin, cos) = angle.sin_cos();
+    (
+        add(scale(a, cos), scale(b, sin)),
+        add(scale(a, -sin), scale(b, cos)),
+    )
+}
+
+#[derive(Clone, Copy, Debug)]
+struct State {
+    position: Point3,
+    heading: Point3,
+    left: Point3,
+    up: Point3,
+    step: f64,
+}
+
+impl<Symbol> Turtle3<Symbol>
+where
+    Symbol: Eq + Hash,
+{
+    /// a turtle that doesn't know any symbols yet; `angle` in degrees.
+    pub fn new(angle: f64) -> Self {
+        Self {
+            actions: HashMap::new(),
+            angle: angle.to_radians(),
+            step: 1.,
+        }
+    }
+
+    pub fn map(mut self, symbol: Symbol, action: Action) -> Self {
+        self.actions.insert(symbol, action);
+        self
+    }
+
+    pub fn step(mut self, step: f64) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// walks `symbols`, returning the lines drawn, in the same way as the 2D turtle.
+    pub fn draw<I>(&self, symbols: I) -> Vec<Polyline3>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Symbol>,
+    {
+        let mut state = State {
+            position: (0., 0., 0.),
+            heading: (0., 1., 0.),
+            left: (-1., 0., 0.),
+            up: (0., 0., 1.),
+            step: self.step,
+        };
+        let mut stack = Vec::new();
+        let mut lines = Vec::new();
+        let mut line = vec![state.position];
+
+        fn lift(line: &mut Polyline3, lines: &mut Vec<Polyline3>, at: Point3) {
+            let finished = std::mem::replace(line, vec![at]);
+            if finished.len() > 1 {
+                lines.push(finished);
+            }
+        }
+
+        for symbol in symbols {
+            let action = match self.actions.get(symbol.borrow()) {
+                Some(&action) => action,
+                None => continue,
+            };
+
+            let angle = self.angle;
+            match action {
+                Action::Draw | Action::Move => {
+                    state.position = add(state.position, scale(state.heading, state.step));
+                    if action == Action::Draw {
+                        line.push(state.position);
+                    } else {
+                        lift(&mut line, &mut lines, state.position);
+                    }
+                }
+                Action::YawLeft => {
+                    let (h, l) = rotate(state.heading, state.left, angle);
+                    state.heading = h;
+                    state.left = l;
+                }
+                Action::YawRight => {
+                    let (h, l) = rotate(state.heading, state.left, -angle);
+                    state.heading = h;
+                    state.left = l;
+                }
+                Action::PitchDown => {
+                    let (h, u) = rotate(state.heading, state.up, -angle);
+                    state.heading = h;
+                    state.up = u;
+                }
+                Action::PitchUp => {
+                    let (h, u) = rotate(state.heading, state.up, angle);
+                    state.heading = h;
+                    state.up = u;
+                }
+                Action::RollLeft => {
+                    let (l, u) = rotate(state.left, state.up, -angle);
+                    state.left = l;
+                    state.up = u;
+                }
+                Action::RollRight => {
+                    let (l, u) = rotate(state.left, state.up, angle);
+                    state.left = l;
+                    state.up = u;
+                }
+                Action::TurnAround => {
+                    state.heading = scale(state.heading, -1.);
+                    state.left = scale(state.left, -1.);
+                }
+                Action::Push => stack.push(state),
+                Action::Pop => {
+                    if let Some(saved) = stack.pop() {
+                        state = saved;
+                        lift(&mut line, &mut lines, state.position);
+                    }
+                }
+                Action::Scale(factor) => state.step *= factor,
+            }
+        }
+        lift(&mut line, &mut lines, state.position);
+
+        lines
+    }
+}
+
+impl Turtle3<char> {
+    /// `F` draws, `f` moves, `+ - & ^ \ /` rotate, `|` turns around, and `[`/`]` branch. `angle`
+    /// in degrees.
+    pub fn standard(angle: f64) -> Self {
+        Self::new(angle)
+            .map('F', Action::Draw)
+            .map('f', Action::Move)
+            .map('+', Action::YawLeft)
+            .map('-', Action::YawRight)
+            .map('&', Action::PitchDown)
+            .map('^', Action::PitchUp)
+            .map('\\', Action::RollLeft)
+            .map('/', Action::RollRight)
+            .map('|', Action::TurnAround)
+            .map('[', Action::Push)
+            .map(']', Action::Pop)
+    }
+}
+
+/// how far short of a perspective camera's eye lines are cut, as a fraction of its distance.
+const NEAR: f64 = 1e-3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Orthographic,
+    /// with the eye this far from the origin.
+    Perspective {
+        distance: f64,
+    },
+}
+
+/// looks at the origin from a direction given by `yaw` about the vertical (y) axis and then
+/// `pitch` about the horizontal one, both in radians; zero for both looks down -z.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub yaw: f64,
+    pub pitch: f64,
+    pub projection: Projection,
+}
+
+/// a projected line segment, with the depth (towards the eye) of each end.
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    from: (f64, f64),
+    to: (f64, f64),
+    depth: (f64, f64),
+}
+
+impl Segment {
+    /// where along `self` and `other` (from 0 to 1) they cross, if they do.
+    fn crossing(&self, other: &Segment) -> Option<(f64, f64)> {
+        let d1 = (self.to.0 - self.from.0, self.to.1 - self.from.1);
+        let d2 = (other.to.0 - other.from.0, other.to.1 - other.from.1);
+        let denominator = d1.0 * d2.1 - d1.1 * d2.0;
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+
+        let e = (other.from.0 - self.from.0, other.from.1 - self.from.1);
+        let s = (e.0 * d2.1 - e.1 * d2.0) / denominator;
+        let t = (e.0 * d1.1 - e.1 * d1.0) / denominator;
+        if (0.0..=1.).contains(&s) && (0.0..=1.).contains(&t) {
+            Some((s, t))
+        } else {
+            None
+        }
+    }
+
+    fn depth_at(&self, s: f64) -> f64 {
+        self.depth.0 + s * (self.depth.1 - self.depth.0)
+    }
+
+    fn at(&self, s: f64) -> (f64, f64) {
+        (
+            self.from.0 + s * (self.to.0 - self.from.0),
+            self.from.1 + s * (self.to.1 - self.from.1),
+        )
+    }
+
+    fn length(&self) -> f64 {
+        (self.to.0 - self.from.0).hypot(self.to.1 - self.from.1)
+    }
+}
+
+impl Camera {
+    /// an orthographic camera; angles in degrees.
+    pub fn new(yaw: f64, pitch: f64) -> Self {
+        Self {
+            yaw: yaw.to_radians(),
+            pitch: pitch.to_radians(),
+            projection: Projection::Orthographic,
+        }
+    }
+
+    pub fn perspective(mut self, distance: f64) -> Self {
+        self.projection = Projection::Perspective { distance };
+        self
+    }
+
+    /// `point` in view space: x right, y up, z towards the eye.
+    fn view(&self, (x, y, z): Point3) -> Point3 {
+        let (sin, cos) = self.yaw.sin_cos();
+        let (x, z) = (x * cos - z * sin, x * sin + z * cos);
+        let (sin, cos) = self.pitch.sin_cos();
+        let (y, z) = (y * cos - z * sin, y * sin + z * cos);
+        (x, y, z)
+    }
+
+    /// where `point`, in view space, lands on the page, and its depth.
+    fn flatten(&self, (x, y, z): Point3) -> ((f64, f64), f64) {
+        match self.projection {
+            Projection::Orthographic => ((x, y), z),
+            Projection::Perspective { distance } => {
+                let k = distance / (distance - z);
+                ((x * k, y * k), z)
+            }
+        }
+    }
+
+    fn segments(&self, lines: &[Polyline3]) -> Vec<Vec<Segment>> {
+        // the perspective blows up at the eye, so lines are cut a little short of it
+        let near = match self.projection {
+            Projection::Orthographic => f64::INFINITY,
+            Projection::Perspective { distance } => distance * (1. - NEAR),
+        };
+        // the point between `a` and `b` at depth `near`
+        let cut = |a: Point3, b: Point3| {
+            let t = (near - a.2) / (b.2 - a.2);
+            add(a, scale(add(b, scale(a, -1.)), t))
+        };
+
+        let mut projected = Vec::new();
+        for line in lines {
+            let mut segments = Vec::new();
+            for pair in line.windows(2) {
+                let (a, b) = (self.view(pair[0]), self.view(pair[1]));
+                let behind = (a.2 >= near, b.2 >= near);
+                if behind.0 && behind.1 {
+                    if !segments.is_empty() {
+                        projected.push(std::mem::take(&mut segments));
+                    }
+                    continue;
+                }
+
+                let (a, b) = match behind {
+                    (true, _) => (cut(a, b), b),
+                    (_, true) => (a, cut(a, b)),
+                    _ => (a, b),
+                };
+                let ((from, d0), (to, d1)) = (self.flatten(a), self.flatten(b));
+                segments.push(Segment {
+                    from,
+                    to,
+                    depth: (d0, d1),
+                });
+                if behind.1 {
+                    projected.push(std::mem::take(&mut segments));
+                }
+            }
+            if !segments.is_empty() {
+                projected.push(segments);
+            }
+        }
+
+        projected
+    }
+
+    /// flattens `lines` onto the page. lines passing behind the eye are cut just in front of it.
+    pub fn project(&self, lines: &[Polyline3]) -> Vec<Polyline> {
+        self.segments(lines)
+            .into_iter()
+            .map(|segments| {
+                let mut line = vec![segments[0].from];
+                line.extend(segments.iter().map(|segment| segment.to));
+                line
+            })
+            .collect()
+    }
+
+    /// like `project`, but wherever two lines cross on the page, the one further from the eye
+    /// gets a gap of `halo` either side of the crossing, so the nearer line reads as in front.
+    pub fn project_hidden(&self, lines: &[Polyline3], halo: f64) -> Vec<Polyline> {
+        let lines = self.segments(lines);
+        let all = lines.iter().flatten().collect::<Vec<_>>();
+
+        let mut visible = Vec::new();
+        for segments in &lines {
+            let mut line: Polyline = Vec::new();
+            for segment in segments {
+                let length = segment.length();
+
+                // the parts of this segment that are hidden, from 0 to 1
+                let mut gaps = Vec::new();
+                if length > 0. {
+                    for other in &all {
+                        if let Some((s, t)) = segment.crossing(other) {
+                            if segment.depth_at(s) < other.depth_at(t) {
+                                gaps.push((s - halo / length, s + halo / length));
+                            }
+                        }
+                    }
+                }
+                gaps.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+                let mut s = 0.;
+                for (start, end) in gaps.into_iter().chain(Some((1., 1.))) {
+                    if start > s {
+                        let from = segment.at(s);
+                        if line.last() != Some(&from) {
+                            if line.len() > 1 {
+                                visible.push(std::mem::take(&mut line));
+                            }
+                            line = vec![from];
+                        }
+                        line.push(segment.at(start.min(1.)));
+                    }
+                    s = s.max(end);
+                    if s >= 1. {
+                        break;
+                    }
+                }
+            }
+            if line.len() > 1 {
+                visible.push(line);
+            }
+        }
+
+        visible
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn end(symbols: &str) -> (i64, i64, i64) {
+        let lines = Turtle3::standard(90.).draw(symbols.chars());
+        let (x, y, z) = *lines.last().unwrap().last().unwrap();
+        (x.round() as i64, y.round() as i64, z.round() as i64)
+    }
+
+    #[test]
+    fn rotations() {
+        assert_eq!(end("F"), (0, 1, 0));
+        assert_eq!(end("+F"), (-1, 0, 0));
+        assert_eq!(end("-F"), (1, 0, 0));
+        assert_eq!(end("&F"), (0, 0, -1));
+        assert_eq!(end("^F"), (0, 0, 1));
+        assert_eq!(end("|F"), (0, -1, 0));
+        // rolling first changes which way pitching goes
+        assert_eq!(end("/&F"), (-1, 0, 0));
+        assert_eq!(end("\\&F"), (1, 0, 0));
+        assert_eq!(end("\\\\\\\\&F"), (0, 0, -1));
+    }
+
+    #[test]
+    fn projection() {
+        let lines = vec![vec![(0., 0., 0.), (0., 1., 1.)]];
+
+        let front = Camera::new(0., 0.).project(&lines);
+        assert_eq!(front, vec![vec![(0., 0.), (0., 1.)]]);
+
+        // from the side, the line's depth shows up horizontally
+        let side = Camera::new(90., 0.).project(&lines);
+        assert!((side[0][1].0 - -1.).abs() < 1e-9 && (side[0][1].1 - 1.).abs() < 1e-9);
+
+        // nearer things look bigger
+        let close = Camera::new(0., 0.).perspective(2.).project(&lines);
+        assert!((close[0][1].1 - 2.).abs() < 1e-9);
+
+        // and things behind the eye aren't drawn
+        let behind = Camera::new(0., 0.)
+            .perspective(0.5)
+            .project(&[vec![(0., 0., 1.), (0., 1., 1.)]]);
+        assert!(behind.is_empty());
+    }
+
+    #[test]
+    fn lines_through_the_eye_are_cut() {
+        // out past the eye and back again
+        let lines = vec![vec![(0., 0., 0.), (0., 2., 2.), (1., 0., 0.)]];
+        let cut = Camera::new(0., 0.).perspective(1.).project(&lines);
+        assert_eq!(cut.len(), 2);
+        assert_eq!((cut[0].len(), cut[1].len()), (2, 2));
+        assert_eq!(cut[0][0], (0., 0.));
+        assert_eq!(cut[1][1], (1., 0.));
+
+        // the cut ends are just in front of the eye, so they land far out along the line
+        let (x, y) = cut[0][1];
+        assert!(x.abs() < 1e-9 && y > 100. && y.is_finite());
+        let (x, y) = cut[1][0];
+        assert!(x > 100. && y > 100. && x.is_finite() && y.is_finite());
+    }
+
+    #[test]
+    fn hidden_lines_get_gaps() {
+        let lines = vec![
+            vec![(-1., 0., 0.), (1., 0., 0.)],
+            vec![(0., -1., 1.), (0., 1., 1.)],
+        ];
+
+        let visible = Camera::new(0., 0.).project_hidden(&lines, 0.5);
+        assert_eq!(
+            visible,
+            vec![
+                vec![(-1., 0.), (-0.5, 0.)],
+                vec![(0.5, 0.), (1., 0.)],
+                vec![(0., -1.), (0., 1.)],
+            ]
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! space-filling curves, computed directly: from an index along the curve to a cell of the grid,
+//! and back again, at any order, without expanding a grammar.
+//!
+//! handy for drawing the curves themselves, and for sorting things spatially: points that are
+//! close along a curve are close on the page.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    Hilbert,
+    /// hilbert's curve, closed into a loop.
+    Moore,
+    /// on a grid of side 3^order rather than 2^order.
+    Peano,
+    /// morton order. not continuous: it jumps between quadrants.
+    ZOrder,
+}
+
+impl Curve {
+    pub const ALL: [Curve; 4] = [Curve::Hilbert, Curve::Moore, Curve::Peano, Curve::ZOrder];
+
+    /// the highest order whose grid side still fits in a `u32`.
+    pub fn max_order(self) -> u32 {
+        match self {
+            Curve::Peano => 20,
+            _ => 31,
+        }
+    }
+
+    /// how many cells along each side of the grid at `order`. panics past `max_order`.
+    pub fn side(self, order: u32) -> u32 {
+        let side = match self {
+            Curve::Peano => 3u32.checked_pow(order),
+            _ => 1u32.checked_shl(order),
+        };
+        side.unwrap_or_else(|| panic!("{} curves only go up to order {}", self, self.max_order()))
+    }
+
+    /// how many cells the curve visits at `order`.
+    pub fn len(self, order: u32) -> u64 {
+        let side = self.side(order) as u64;
+        side * side
+    }
+
+    /// the cell `index` steps along the curve.
+    pub fn point(self, order: u32, index: u64) -> (u32, u32) {
+        debug_assert!(index < self.len(order));
+        match self {
+            Curve::Hilbert => hilbert_point(order, index),
+            Curve::Moore => moore_point(order, index),
+            Curve::Peano => peano_point(order, index),
+            Curve::ZOrder => (compact(index), compact(index >> 1)),
+        }
+    }
+
+    /// how far along the curve cell `(x, y)` is.
+    pub fn index(self, order: u32, (x, y): (u32, u32)) -> u64 {
+        debug_assert!(x < self.side(order) && y < self.side(order));
+        match self {
+            Curve::Hilbert => hilbert_index(order, (x, y)),
+            Curve::Moore => moore_index(order, (x, y)),
+            Curve::Peano => peano_index(order, (x, y)),
+            Curve::ZOrder => spread(x) | spread(y) << 1,
+        }
+    }
+
+    /// how far along the curve the point `(x, y)` in the unit square is; points outside it are
+    /// clamped to the edge.
+    pub fn key(self, order: u32, (x, y): (f64, f64)) -> u64 {
+        let side = self.side(order);
+        let cell = |t: f64| ((t * side as f64) as i64).max(0).min(side as i64 - 1) as u32;
+        self.index(order, (cell(x), cell(y)))
+    }
+
+    /// the whole curve, through the centre of each cell, scaled into the unit square.
+    pub fn polyline(self, order: u32) -> Vec<(f64, f64)> {
+        let side = self.side(order) as f64;
+        (0..self.len(order))
+            .map(|i| {
+                let (x, y) = self.point(order, i);
+                ((x as f64 + 0.5) / side, (y as f64 + 0.5) / side)
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Curve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hilbert" => Ok(Curve::Hilbert),
+            "moore" => Ok(Curve::Moore),
+            "peano" => Ok(Curve::Peano),
+            "z" | "zorder" | "z-order" | "morton" => Ok(Curve::ZOrder),
+            _ => Err(format!(
+                "unknown curve {:?} (expected hilbert, moore, peano or zorder)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Curve::Hilbert => "hilbert",
+            Curve::Moore => "moore",
+            Curve::Peano => "peano",
+            Curve::ZOrder => "zorder",
+        };
+        f.write_str(name)
+    }
+}
+
+/// flips the quadrant about its diagonal, as hilbert's curve does going down a level.
+fn rotate(side: u32, (x, y): (u32, u32), rx: u32, ry: u32) -> (u32, u32) {
+    if ry == 0 {
+        if rx == 1 {
+            (side - 1 - y, side - 1 - x)
+        } else {
+            (y, x)
+        }
+    } else {
+        (x, y)
+    }
+}
+
+// after wikipedia's `d2xy`/`xy2d`: starts at (0, 0) and ends at (side - 1, 0).
+fn hilbert_point(order: u32, index: u64) -> (u32, u32) {
+    let mut t = index;
+    let (mut x, mut y) = (0, 0);
+    for level in 0..order {
+        let s = 1 << level;
+        let rx = (1 & (t / 2)) as u32;
+        let ry = (1 & (t ^ rx as u64)) as u32;
+        let rotated = rotate(s, (x, y), rx, ry);
+        x = rotated.0 + s * rx;
+        y = rotated.1 + s * ry;
+        t /= 4;
+    }
+
+    (x, y)
+}
+
+fn hilbert_index(order: u32, (mut x, mut y): (u32, u32)) -> u64 {
+    let side = 1 << order;
+    let mut index = 0;
+    for level in (0..order).rev() {
+        let s = 1 << level;
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        index += (s as u64 * s as u64) * ((3 * rx) ^ ry) as u64;
+        let rotated = rotate(side, (x, y), rx, ry);
+        x = rotated.0;
+        y = rotated.1;
+    }
+
+    index
+}
+
+// four hilbert curves of one order lower, one per quadrant, entering and leaving through the
+// middle of the bottom edge: up the left half, then down the right.
+fn moore_point(order: u32, index: u64) -> (u32, u32) {
+    if order == 0 {
+        return (0, 0);
+    }
+
+    let h = 1 << (order - 1);
+    let quadrant_len = h as u64 * h as u64;
+    let (x, y) = hilbert_point(order - 1, index % quadrant_len);
+    match index / quadrant_len {
+        0 => (h - 1 - y, x),
+        1 => (h - 1 - y, x + h),
+        2 => (h + y, 2 * h - 1 - x),
+        _ => (h + y, h - 1 - x),
+    }
+}
+
+fn moore_index(order: u32, (x, y): (u32, u32)) -> u64 {
+    if order == 0 {
+        return 0;
+    }
+
+    let h = 1 << (order - 1);
+    let quadrant_len = h as u64 * h as u64;
+    let (quadrant, point) = match (x < h, y < h) {
+        (true, true) => (0, (y, h - 1 - x)),
+        (true, false) => (1, (y - h, h - 1 - x)),
+        (false, false) => (2, (2 * h - 1 - y, x - h)),
+        (false, true) => (3, (h - 1 - y, x - h)),
+    };
+
+    quadrant * quadrant_len + hilbert_index(order - 1, point)
+}
+
+/// the base-3 digits of `index`, most significant first.
+fn ternary(index: u64, digits: u32) -> Vec<u32> {
+    (0..digits)
+        .rev()
+        .map(|i| (index / 3u64.pow(i) % 3) as u32)
+        .collect()
+}
+
+/// `digit`, mirrored if `parity` is odd.
+fn reflect(digit: u32, parity: u32) -> u32 {
+    if parity & 1 == 0 {
+        digit
+    } else {
+        2 - digit
+    }
+}
+
+/// peano's own definition: the index's ternary digits alternate between x and y, each one
+/// reflected if the digits already given to the other coordinate sum to something odd.
+fn peano_point(order: u32, index: u64) -> (u32, u32) {
+    let digits = ternary(index, 2 * order);
+
+    let (mut x, mut y) = (0, 0);
+    let (mut x_sum, mut y_sum) = (0, 0);
+    for pair in digits.chunks(2) {
+        x = 3 * x + reflect(pair[0], y_sum);
+        x_sum += pair[0];
+        y = 3 * y + reflect(pair[1], x_sum);
+        y_sum += pair[1];
+    }
+
+    (x, y)
+}
+
+fn peano_index(order: u32, (x, y): (u32, u32)) -> u64 {
+    let (xs, ys) = (ternary(x as u64, order), ternary(y as u64, order));
+
+    let mut index = 0;
+    let (mut x_sum, mut y_sum) = (0, 0);
+    for (&xd, &yd) in xs.iter().zip(&ys) {
+        let first = reflect(xd, y_sum);
+        x_sum += first;
+        let second = reflect(yd, x_sum);
+        y_sum += second;
+        index = 9 * index + 3 * first as u64 + second as u64;
+    }
+
+    index
+}
+
+/// every other bit of `n`, squashed together.
+fn compact(n: u64) -> u32 {
+    (0..32).fold(0, |acc, bit| acc | (((n >> (2 * bit)) & 1) as u32) << bit)
+}
+
+/// the bits of `n`, spaced out with zeroes between them.
+fn spread(n: u32) -> u64 {
+    (0..32).fold(0, |acc, bit| acc | ((n as u64 >> bit) & 1) << (2 * bit))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn adjacent(a: (u32, u32), b: (u32, u32)) -> bool {
+        let dx = (a.0 as i64 - b.0 as i64).abs();
+        let dy = (a.1 as i64 - b.1 as i64).abs();
+        dx + dy == 1
+    }
+
+    #[test]
+    fn visits_every_cell_once_and_inverts() {
+        for &curve in &Curve::ALL {
+            for order in 0..5 {
+                let points = (0..curve.len(order))
+                    .map(|i| curve.point(order, i))
+                    .collect::<Vec<_>>();
+
+                let side = curve.side(order);
+                assert!(points.iter().all(|&(x, y)| x < side && y < side));
+                assert_eq!(
+                    points.iter().collect::<HashSet<_>>().len() as u64,
+                    curve.len(order),
+                    "{} {}",
+                    curve,
+                    order
+                );
+                for (i, &point) in points.iter().enumerate() {
+                    assert_eq!(curve.index(order, point), i as u64, "{} {}", curve, order);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn continuous() {
+        for &curve in &[Curve::Hilbert, Curve::Moore, Curve::Peano] {
+            for order in 1..5 {
+                let points = (0..curve.len(order))
+                    .map(|i| curve.point(order, i))
+                    .collect::<Vec<_>>();
+                assert!(
+                    points.windows(2).all(|pair| adjacent(pair[0], pair[1])),
+                    "{} {}",
+                    curve,
+                    order
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ends() {
+        let last = |curve: Curve, order| curve.point(order, curve.len(order) - 1);
+
+        assert_eq!(Curve::Hilbert.point(3, 0), (0, 0));
+        assert_eq!(last(Curve::Hilbert, 3), (7, 0));
+        assert_eq!(Curve::Peano.point(2, 0), (0, 0));
+        assert_eq!(last(Curve::Peano, 2), (8, 8));
+        // moore's curve closes up
+        for order in 1..5 {
+            assert!(adjacent(
+                Curve::Moore.point(order, 0),
+                last(Curve::Moore, order)
+            ));
+        }
+        assert_eq!(
+            (0..4)
+                .map(|i| Curve::ZOrder.point(1, i))
+                .collect::<Vec<_>>(),
+            &[(0, 0), (1, 0), (0, 1), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn max_orders_fit() {
+        for &curve in &Curve::ALL {
+            let order = curve.max_order();
+            assert!(curve.side(order) > curve.side(order - 1));
+            assert!(curve.len(order) > curve.len(order - 1));
+            assert!(std::panic::catch_unwind(|| curve.side(order + 1)).is_err());
+        }
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(Curve::Hilbert.key(1, (0.1, 0.1)), 0);
+        assert_eq!(Curve::Hilbert.key(1, (0.9, -3.)), 3);
+        assert_eq!(Curve::Hilbert.key(1, (0.2, 1.)), 1);
+
+        let line = Curve::Hilbert.polyline(1);
+        assert_eq!(
+            line,
+            &[(0.25, 0.25), (0.25, 0.75), (0.75, 0.75), (0.75, 0.25)]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Sound as something to draw: FLAC decoding, and the spectra and loudness of a recording over
+//! time.
+
+use num_complex::Complex32;
+use std::f32::consts::PI;
+use std::path::Path;
+
+/// A mono recording, mixed down from however many channels it had, with samples in `-1.0..1.0`.
+#[derive(Clone, Debug)]
+pub struct Audio {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+impl Audio {
+    pub fn new(sample_rate: u32, samples: Vec<f32>) -> Self {
+        Self {
+            sample_rate,
+            samples,
+        }
+    }
+
+    /// Decodes a FLAC file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, claxon::Error> {
+        let mut reader = claxon::FlacReader::open(path)?;
+        let info = reader.streaminfo();
+        let channels = info.channels as usize;
+        let scale = 1. / (1u64 << (info.bits_per_sample - 1)) as f32 / channels as f32;
+
+        let mut samples = Vec::with_capacity(info.samples.unwrap_or(0) as usize);
+        let (mut mixed, mut channel) = (0., 0);
+        for sample in reader.samples() {
+            mixed += sample? as f32;
+            channel += 1;
+            if channel == channels {
+                samples.push(mixed * scale);
+                mixed = 0.;
+                channel = 0;
+            }
+        }
+
+        Ok(Self::new(info.sample_rate, samples))
+    }
+
+    /// How long the recording lasts, in seconds.
+    pub fn duration(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate as f64
+    }
+
+    /// The samples from `start` to `end` seconds in, clamped to the recording; everything from
+    /// `start` onwards without an `end`.
+    pub fn clip(&self, start: f64, end: Option<f64>) -> &[f32] {
+        let index =
+            |t: f64| ((t * self.sample_rate as f64).max(0.) as usize).min(self.samples.len());
+        let (from, to) = (index(start), end.map_or(self.samples.len(), index));
+        &self.samples[from..to.max(from)]
+    }
+
+    /// The frequency in Hz of bin `bin` of a spectrum from `window`-sample windows.
+    pub fn frequency(&self, bin: usize, window: usize) -> f64 {
+        bin as f64 * self.sample_rate as f64 / window as f64
+    }
+
+    /// The bins of a spectrum from `window`-sample windows covering `low` to `high` Hz.
+    pub fn bins(&self, window: usize, low: f64, high: f64) -> std::ops::Range<usize> {
+        let bin = |f: f64| {
+            ((f * window as f64 / self.sample_rate as f64)
+                .round()
+                .max(0.) as usize)
+                .min(window / 2)
+        };
+        bin(low)..bin(high).max(bin(low) + 1).min(window / 2 + 1)
+    }
+}
+
+/// The magnitude spectra of `rows` evenly spaced, Hann-windowed stretches of `samples`, each
+/// `window` samples long (a power of two, ideally) and with `window / 2 + 1` bins from 0 Hz up
+/// to the Nyquist frequency. Windows overlap when there's more rows than the samples can fill
+/// side by side; windows running off the end are padded with silence.
+pub fn spectrogram(samples: &[f32], window: usize, rows: usize) -> Vec<Vec<f32>> {
+    let fft = fourier::create_fft_f32(window);
+    let hann = (0..window)
+        .map(|i| 0.5 - 0.5 * (2. * PI * i as f32 / window as f32).cos())
+        .collect::<Vec<_>>();
+    let hop = samples.len().saturating_sub(window) as f64 / rows.saturating_sub(1).max(1) as f64;
+
+    let mut buffer = vec![Complex32::new(0., 0.); window];
+    (0..rows)
+        .map(|row| {
+            let start = (row as f64 * hop) as usize;
+            for (i, value) in buffer.iter_mut().enumerate() {
+                let sample = samples.get(start + i).copied().unwrap_or(0.);
+                *value = Complex32::new(sample * hann[i], 0.);
+            }
+            fft.fft_in_place(&mut buffer);
+            // hann's window halves the amplitude; normalize so a full-scale sine peaks near one
+            buffer[..=window / 2]
+                .iter()
+                .map(|c| c.norm() * 4. / window as f32)
+                .collect()
+        })
+        .collect()
+}
+
+/// The loudest sample in each of `count` equal stretches of `samples`.
+pub fn envelope(samples: &[f32], count: usize) -> Vec<f32> {
+    (0..count)
+        .map(|i| {
+            let (from, to) = (i * samples.len() / count, (i + 1) * samples.len() / count);
+            samples[from..to.max(from)]
+                .iter()
+                .fold(0f32, |peak, s| peak.max(s.abs()))
+        })
+        .collect()
+}
+
+/// Averages `values` down (or repeats them up) to `count` evenly spaced values.
+pub fn resample(values: &[f32], count: usize) -> Vec<f32> {
+    if values.is_empty() {
+        return vec![0.; count];
+    }
+
+    (0..count)
+        .map(|i| {
+            let from = i * values.len() / count;
+            let to = ((i + 1) * values.len() / count).max(from + 1);
+            values[from..to].iter().sum::<f32>() / (to - from) as f32
+        })
+        .collect()
+}
+
+/// `magnitude` in decibels below `reference`, mapped from `-range` dB (or quieter) at `0.0` up
+/// to `1.0` at the reference.
+pub fn decibels(magnitude: f32, reference: f32, range: f32) -> f32 {
+    let db = 20. * (magnitude / reference).max(1e-12).log10();
+    ((db + range) / range).clamp(0., 1.)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tone(frequency: f32, sample_rate: u32, seconds: f32) -> Audio {
+        let samples = (0..(sample_rate as f32 * seconds) as usize)
+            .map(|i| (2. * PI * frequency * i as f32 / sample_rate as f32).sin())
+            .collect();
+        Audio::new(sample_rate, samples)
+    }
+
+    #[test]
+    fn spectrogram_finds_the_tone() {
+        let audio = tone(1000., 8000, 1.);
+        let rows = spectrogram(&audio.samples, 256, 10);
+        assert_eq!(rows.len(), 10);
+        for row in &rows {
+            assert_eq!(row.len(), 129);
+            let peak = (0..row.len())
+                .max_by(|&a, &b| row[a].partial_cmp(&row[b]).unwrap())
+                .unwrap();
+            assert_eq!(audio.frequency(peak, 256), 1000.);
+            assert!((row[peak] - 1.).abs() < 0.05, "{}", row[peak]);
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let audio = tone(1000., 8000, 2.);
+        assert_eq!(audio.duration(), 2.);
+        assert_eq!(audio.clip(0.5, Some(1.)).len(), 4000);
+        assert_eq!(audio.clip(1.5, None).len(), 4000);
+        assert!(audio.clip(3., Some(4.)).is_empty());
+        assert_eq!(audio.bins(256, 0., 4000.), 0..128);
+        assert_eq!(audio.bins(256, 1000., 2000.), 32..64);
+    }
+
+    #[test]
+    fn envelopes_and_resampling() {
+        let samples = [0., -0.5, 0.25, 1., 0., 0.];
+        assert_eq!(envelope(&samples, 3), &[0.5, 1., 0.]);
+        assert_eq!(resample(&samples, 2), &[-0.25 / 3., 1. / 3.]);
+        assert_eq!(resample(&[1., 2.], 4), &[1., 1., 2., 2.]);
+        assert_eq!(decibels(1., 1., 60.), 1.);
+        assert!((decibels(0.001, 1., 60.)).abs() < 1e-6);
+        assert_eq!(decibels(0., 1., 60.), 0.);
+    }
+}
@@ -1,115 +1,171 @@
-use hpgl::hp7470a::{DeviceControlInstruction, HandshakeConfig, HandshakeMode};
-use hpgl::{Coordinate, HpglCommand, HpglProgram, PlotterWriteable};
-use serialport::{self, DataBits, FlowControl, Parity, SerialPortSettings, StopBits};
-use std::io::{self, Write};
-use std::thread;
-use std::time::Duration;
-
-use delaunator::{triangulate, Point};
-use rand::prelude::*;
-
-fn gen_points(n: usize) -> Vec<Point> {
-    let mut rng = rand::thread_rng();
-
-    let margin = 500.;
-
-    (0..n)
-        .map(|_| {
-            let x = rng.gen_range(0. + margin, Coordinate::MAX_X_US as f64 - margin);
-            let y = rng.gen_range(0. + margin, Coordinate::MAX_Y as f64 - margin);
-            Point { x, y }
-        })
-        .collect()
+use hpgl::{Coordinate, HpglCommand, HpglProgram};
+use plotterart::delaunay::{self, Diagram, Polyline};
+use plotterart::output::OutputOptions;
+use plotterart::page::PageOptions;
+use plotterart::sample::SampleOptions;
+use plotterart::seed::{self, SeedOptions};
+use serde::Serialize;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Layer {
+    Triangles,
+    Voronoi,
+    Circles,
+    Hull,
+    Gabriel,
+    Rng,
+    Mst,
+}
+
+impl FromStr for Layer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "triangles" => Ok(Layer::Triangles),
+            "voronoi" => Ok(Layer::Voronoi),
+            "circles" => Ok(Layer::Circles),
+            "hull" => Ok(Layer::Hull),
+            "gabriel" => Ok(Layer::Gabriel),
+            "rng" => Ok(Layer::Rng),
+            "mst" => Ok(Layer::Mst),
+            _ => Err(format!(
+                "unknown layer {:?} (expected triangles, voronoi, circles, hull, gabriel, rng or mst)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, StructOpt)]
+struct Params {
+    /// Number of points to triangulate
+    #[structopt(long, default_value = "100")]
+    points: usize,
+    #[structopt(flatten)]
+    sample: SampleOptions,
+    #[structopt(flatten)]
+    page: PageOptions,
+    /// What to draw, comma-separated: triangles, voronoi, circles (circumcircles), hull,
+    /// gabriel, rng (relative neighbourhood graph) and mst (minimum spanning tree)
+    #[structopt(long, default_value = "triangles", use_delimiter = true)]
+    draw: Vec<Layer>,
 }
 
-fn gen_program() -> HpglProgram {
+#[derive(StructOpt)]
+#[structopt(
+    name = "01-triangulate",
+    about = "Delaunay triangulation, Voronoi diagram and friends of random points"
+)]
+struct Opt {
+    #[structopt(flatten)]
+    params: Params,
+    #[structopt(flatten)]
+    seed: SeedOptions,
+    #[structopt(flatten)]
+    output: OutputOptions,
+}
+
+fn layer(diagram: &Diagram, layer: Layer) -> Vec<Polyline> {
+    match layer {
+        Layer::Triangles => diagram.lines(&diagram.edges()),
+        Layer::Voronoi => diagram.voronoi_edges(),
+        Layer::Circles => diagram.circumcircles(64),
+        Layer::Hull => vec![diagram.hull()],
+        Layer::Gabriel => diagram.lines(&diagram.gabriel_graph()),
+        Layer::Rng => diagram.lines(&diagram.relative_neighbourhood_graph()),
+        Layer::Mst => diagram.lines(&diagram.minimum_spanning_tree()),
+    }
+}
+
+fn gen_program(params: &Params, seed: u64) -> Result<HpglProgram, Box<dyn std::error::Error>> {
     let mut program = vec![
         HpglCommand::InitializePlotter,
         HpglCommand::SelectPen { pen: 1 },
     ];
 
-    let points = gen_points(100);
-    let triangulation = triangulate(&points).expect("no triangulation found");
-
-    // naive asf
-    for triangle in triangulation.triangles.chunks(3) {
-        program.push(HpglCommand::PlotAbsolute(
-            Coordinate {
-                x: points[triangle[0]].x as f32,
-                y: points[triangle[0]].y as f32,
-            }
-            .into(),
-        ));
-        program.push(HpglCommand::PenDown);
-        program.push(HpglCommand::PlotAbsolute(
-            vec![
-                Coordinate {
-                    x: points[triangle[1]].x as f32,
-                    y: points[triangle[1]].y as f32,
-                },
-                Coordinate {
-                    x: points[triangle[2]].x as f32,
-                    y: points[triangle[2]].y as f32,
-                },
-                Coordinate {
-                    x: points[triangle[0]].x as f32,
-                    y: points[triangle[0]].y as f32,
-                },
-            ]
-            .into(),
-        ));
-        program.push(HpglCommand::PenUp);
+    let area = params.page.area()?;
+    let points = params
+        .sample
+        .points(&mut seed::rng(seed), params.points, area)?;
+    // an image sampler can come back with next to nothing for a pale or tiny image
+    let count = points.len();
+    let diagram = Diagram::new(points).ok_or_else(|| {
+        format!(
+            "can't triangulate {} points: too few, or all in a line",
+            count
+        )
+    })?;
+
+    // circumcentres of slivers on the hull can be far off the page
+    for &l in &params.draw {
+        for line in layer(&diagram, l)
+            .iter()
+            .flat_map(|line| delaunay::clip(line, area))
+        {
+            program.extend(hpgl::polyline(line.into_iter().map(|(x, y)| Coordinate {
+                x: x as f32,
+                y: y as f32,
+            })));
+        }
     }
 
-    program.into()
+    Ok(program.into())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut sp = serialport::open_with_settings(
-        "/dev/ttyUSB0",
-        &SerialPortSettings {
-            baud_rate: 9600,
-            data_bits: DataBits::Eight,
-            stop_bits: StopBits::One,
-            parity: Parity::None,
-            flow_control: FlowControl::Software,
-            timeout: Duration::from_secs(20),
-        },
-    )
-    .expect("can't open serial port");
-
-    // let mut sp = io::stdout();
-
-    // -- configure handshaking
-    DeviceControlInstruction::SetPlotterConfig(Default::default()).write(&mut sp)?;
-    DeviceControlInstruction::SetHandshakeMode(
-        HandshakeMode::Mode2,
-        HandshakeConfig::XonXoff {
-            xoff_threshold: 80,
-            xon_trigger_chars: b"\x11".to_vec(),
-        },
-    )
-    .write(&mut sp)?;
-    DeviceControlInstruction::SetExtHandshakeOptions {
-        interchar_delay: None,
-        xoff_trigger_chars: b"\x13".to_vec(),
-    }
-    .write(&mut sp)?;
-
-    let program = gen_program();
-    // let program: HpglProgram = vec![
-    //     HpglCommand::InitializePlotter,
-    //     HpglCommand::PlotAbsolute(
-    //         vec![Coordinate {
-    //             x: Coordinate::MAX_X / 2.,
-    //             y: Coordinate::MAX_Y / 2.,
-    //         }]
-    //         .into(),
-    //     ),
-    // ]
-    // .into();
-    println!("{:#?}", program);
-    program.write(&mut sp)?;
+    let opt = Opt::from_args();
+
+    let seed = opt.seed.resolve();
+    let program = gen_program(&opt.params, seed)?;
+    opt.output
+        .emit("01-triangulate", &opt.params, Some(seed), &program)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hpgl::PlotterWriteable;
+    use plotterart::page::Paper;
+    use plotterart::sample::Sampler;
+
+    fn params(sampler: Sampler, points: usize) -> Params {
+        Params {
+            points,
+            sample: SampleOptions {
+                sampler,
+                image: None,
+            },
+            page: PageOptions {
+                paper: Paper::Us,
+                margin: 500.,
+            },
+            draw: vec![Layer::Triangles, Layer::Voronoi],
+        }
+    }
+
+    fn hpgl(sampler: Sampler, seed: u64) -> Vec<u8> {
+        let params = params(sampler, 50);
+        let mut buf: Vec<u8> = Vec::new();
+        gen_program(&params, seed).unwrap().write(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn same_seed_same_plot() {
+        for &sampler in &[Sampler::Uniform, Sampler::Poisson, Sampler::Jittered] {
+            assert_eq!(hpgl(sampler, 1234), hpgl(sampler, 1234));
+            assert_ne!(hpgl(sampler, 1234), hpgl(sampler, 4321));
+        }
+    }
+
+    #[test]
+    fn too_few_points_is_an_error() {
+        assert!(gen_program(&params(Sampler::Uniform, 2), 1234).is_err());
+    }
+}
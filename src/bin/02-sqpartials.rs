@@ -1,131 +1,115 @@
-#![allow(unused_imports, non_snake_case)]
-use core::f64::consts::PI;
-use gnuplot::{Figure, PlotOption};
-use hpgl::hp7470a::{DeviceControlInstruction, HandshakeConfig, HandshakeMode};
-use hpgl::{Coordinate, HpglCommand, HpglProgram, PlotterWriteable};
-use ndarray::prelude::*;
-use serialport::{self, DataBits, FlowControl, Parity, SerialPortSettings, StopBits};
-use std::io::{self, Write};
-use std::thread;
-use std::time::Duration;
+use hpgl::{Coordinate, HpglCommand, HpglProgram};
+use plotterart::output::OutputOptions;
+use plotterart::page::PageOptions;
+use plotterart::partials::{self, Series, Wave};
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, Serialize, StructOpt)]
+struct Params {
+    /// Harmonic bound K; partial sums for k in 1..K are stacked, each adding the next harmonic
+    /// the waveform has
+    #[structopt(long, default_value = "20")]
+    harmonics: usize,
+    /// Waveform to build up: square, sawtooth or triangle
+    #[structopt(long, default_value = "square")]
+    wave: Wave,
+    /// Sine coefficients for harmonics 1, 2, 3, ..., instead of a built-in waveform
+    #[structopt(long, use_delimiter = true, allow_hyphen_values = true)]
+    sines: Vec<f64>,
+    /// Cosine coefficients for harmonics 1, 2, 3, ..., instead of a built-in waveform
+    #[structopt(long, use_delimiter = true, allow_hyphen_values = true)]
+    cosines: Vec<f64>,
+    /// File of samples over one period of a curve, separated by whitespace or commas, to take
+    /// the coefficients from by FFT
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["sines", "cosines"]
+    )]
+    samples: Option<PathBuf>,
+    /// Periods of the wave across the page
+    #[structopt(long, default_value = "4")]
+    periods: f64,
+    /// Samples per partial sum
+    #[structopt(long, default_value = "1000")]
+    grains: usize,
+    /// Vertical offset between successive partial sums
+    #[structopt(long, default_value = "2")]
+    delta_k: f64,
+    /// Vertical scaling of each partial sum, against the offset between them
+    #[structopt(long, default_value = "1")]
+    scale: f64,
+    #[structopt(flatten)]
+    page: PageOptions,
+}
+
+#[derive(StructOpt)]
+#[structopt(
+    name = "02-sqpartials",
+    about = "Stacked partial Fourier sums of a square wave, or any other"
+)]
+struct Opt {
+    #[structopt(flatten)]
+    params: Params,
+    #[structopt(flatten)]
+    output: OutputOptions,
+}
 
-fn gen_program() -> HpglProgram {
+fn series(params: &Params) -> Result<Series, Box<dyn std::error::Error>> {
+    let partials = params.harmonics.saturating_sub(1);
+    Ok(match &params.samples {
+        Some(path) => {
+            let samples = fs::read_to_string(path)?
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|s| !s.is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<f64>, _>>()?;
+            if samples.is_empty() {
+                return Err(format!("no samples in {}", path.display()).into());
+            }
+            Series::from_samples(&samples, samples.len() / 2)
+        }
+        None if !params.sines.is_empty() || !params.cosines.is_empty() => {
+            Series::new(0., params.cosines.clone(), params.sines.clone())
+        }
+        // every other harmonic of the square and triangle waves is missing
+        None => params.wave.series(2 * partials),
+    })
+}
+
+fn gen_program(
+    params: &Params,
+    series: &Series,
+) -> Result<HpglProgram, Box<dyn std::error::Error>> {
     let mut program = vec![
         HpglCommand::InitializePlotter,
         HpglCommand::SelectPen { pen: 1 },
     ];
 
-    let K: f64 = 20.;
-    let t_max: f64 = 4. * 2. * PI;
-    let t_grains = 1000;
-    let omega0: f64 = 1.;
-    let delta_k: f64 = 2.; // bump per k
-    let margin: f64 = 500.;
-
-    let x_ptsize = Coordinate::MAX_X_US as f64 - 2. * margin;
-    let y_ptsize = Coordinate::MAX_Y as f64 - 2. * margin;
-
-    let tt = Array::linspace(0f64, t_max, t_grains);
-    let kk = Array::range(1f64, K, 1.);
-    let mut sintab = Array::<f64, _>::zeros((tt.len(), kk.len()));
-
-    for (i, t) in tt.iter().enumerate() {
-        for (j, k) in kk.iter().enumerate() {
-            sintab[[i, j]] = 4. / PI * (omega0 * t * (2. * k - 1.)).sin() / (2. * k - 1.);
-        }
-    }
-
-    let partials =
-        sintab
-            .gencolumns()
-            .into_iter()
-            .scan(Array::zeros(tt.len()), |running, sinus| {
-                *running += &sinus;
-
-                Some(running.to_owned())
-            });
-
-    // let mut fg = Figure::new();
-    // let mut ax = fg.axes2d();
-    // for (k, partial) in partials.enumerate() {
-    //     ax.lines(
-    //         &tt,
-    //         &(partial + k as f64*2.),
-    //         &[],
-    //     );
-    // }
-    // fg.show().unwrap();
-
-    // figure out y scaling
-    // maximum point we hit is delta_k * K  + 1
-    let max_unscaled_y = delta_k * K;
-    let y_scaling = y_ptsize / max_unscaled_y;
+    let ts = partials::times(params.periods, params.grains);
+    let sums = series.partials(params.harmonics.saturating_sub(1), &ts);
+    let rows = partials::stack(&sums, params.delta_k, params.scale);
 
-    // figure out x scaling
-    // fit max(tt) to MAX_X_US
-    let x_scaling = x_ptsize / t_max;
-
-    for (k, partial) in partials.enumerate() {
-        let partial = &partial + k as f64 * delta_k;
-        program.push(HpglCommand::PlotAbsolute(
-            Coordinate {
-                x: (margin + tt[0] * x_scaling) as f32,
-                y: (margin + partial[0] * y_scaling) as f32,
-            }
-            .into(),
-        ));
-        program.push(HpglCommand::PenDown);
-        program.push(HpglCommand::PlotAbsolute(
-            tt.into_iter()
-                .zip(partial.into_iter())
-                .map(|(t, x)| Coordinate {
-                    x: (margin + t * x_scaling) as f32,
-                    y: (margin + x * y_scaling) as f32,
-                })
-                .collect::<Vec<_>>()
-                .into(),
-        ));
-        program.push(HpglCommand::PenUp);
+    for line in partials::layout(&ts, &rows, params.page.area()?) {
+        program.extend(hpgl::polyline(line.into_iter().map(|(x, y)| Coordinate {
+            x: x as f32,
+            y: y as f32,
+        })));
     }
 
-    program.into()
+    Ok(program.into())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut sp = serialport::open_with_settings(
-        "/dev/ttyUSB0",
-        &SerialPortSettings {
-            baud_rate: 9600,
-            data_bits: DataBits::Eight,
-            stop_bits: StopBits::One,
-            parity: Parity::None,
-            flow_control: FlowControl::Software,
-            timeout: Duration::from_secs(20),
-        },
-    )
-    .expect("can't open serial port");
-
-    // let mut sp = io::stdout();
-
-    // -- configure handshaking
-    DeviceControlInstruction::SetPlotterConfig(Default::default()).write(&mut sp)?;
-    DeviceControlInstruction::SetHandshakeMode(
-        HandshakeMode::Mode2,
-        HandshakeConfig::XonXoff {
-            xoff_threshold: 80,
-            xon_trigger_chars: b"\x11".to_vec(),
-        },
-    )
-    .write(&mut sp)?;
-    DeviceControlInstruction::SetExtHandshakeOptions {
-        interchar_delay: None,
-        xoff_trigger_chars: b"\x13".to_vec(),
-    }
-    .write(&mut sp)?;
+    let opt = Opt::from_args();
 
-    let program = gen_program();
-    println!("{:#?}", program);
-    program.write(&mut sp)?;
+    let series = series(&opt.params)?;
+    let program = gen_program(&opt.params, &series)?;
+    opt.output
+        .emit("02-sqpartials", &opt.params, None, &program)?;
 
     Ok(())
 }
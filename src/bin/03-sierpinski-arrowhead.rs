@@ -1,135 +1,102 @@
-#![allow(unused_imports)]
-use gnuplot::{Figure, PlotOption};
-use hpgl::hp7470a::{DeviceControlInstruction, HandshakeConfig, HandshakeMode};
-use hpgl::{Coordinate, HpglCommand, HpglProgram, PlotterWriteable};
-use serialport::{self, DataBits, FlowControl, Parity, SerialPortSettings, StopBits};
-use std::time::Duration;
-use lsystem::ParametricLSystem;
-use maplit::hashmap;
-use std::collections::HashMap;
+use gnuplot::Figure;
+use hpgl::{Coordinate, HpglCommand, HpglProgram};
+use lsystem::grammar::Grammar;
+use lsystem::presets;
+use lsystem::turtle;
+use plotterart::output::OutputOptions;
+use plotterart::page::PageOptions;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
 
-const PI: f64 = 3.14159;
+#[derive(Debug, Serialize, StructOpt)]
+struct Params {
+    /// L-system grammar file to draw instead of the built-in arrowhead
+    #[structopt(long, parse(from_os_str))]
+    grammar: Option<PathBuf>,
+    /// Built-in L-system to draw instead, e.g. dragon, hilbert, gosper, penrose or plant
+    #[structopt(long, conflicts_with = "grammar")]
+    preset: Option<String>,
+    /// Number of generations to evolve the curve; defaults to the grammar's `iterations`
+    #[structopt(long)]
+    iterations: Option<usize>,
+    #[structopt(flatten)]
+    page: PageOptions,
+}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Symbol {
-    A,
-    B,
-    Left,
-    Right,
+#[derive(StructOpt)]
+#[structopt(name = "03-sierpinski-arrowhead", about = "Sierpiński arrowhead curve")]
+struct Opt {
+    #[structopt(flatten)]
+    params: Params,
+    /// Show the curve in gnuplot before plotting it
+    #[structopt(long)]
+    preview: bool,
+    #[structopt(flatten)]
+    output: OutputOptions,
 }
 
-fn gen_line(lsystem: &ParametricLSystem<Symbol>) -> Vec<(f64, f64)> {
-    let mut line: Vec<(f64, f64)> = vec![(0., 0.)];
-    let mut direction: f64 = 0.; // radians
-    let dt = 1.;
-    for symbol in &lsystem.state {
-        match symbol {
-            Symbol::A | Symbol::B => {
-                let mut pt = line.last().unwrap().clone();
-                pt.0 += dt * direction.cos();
-                pt.1 += dt * direction.sin();
-                line.push(pt);
-            }
-            Symbol::Left => {
-                direction -= PI / 3.;
-            }
-            Symbol::Right => {
-                direction += PI / 3.;
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+
+    let grammar: Grammar = match &opt.params.grammar {
+        Some(path) => fs::read_to_string(path)?.parse()?,
+        None => {
+            let name = opt
+                .params
+                .preset
+                .as_deref()
+                .unwrap_or("sierpinski-arrowhead");
+            match presets::find(name) {
+                Some(preset) => preset.grammar(),
+                None => {
+                    let names = presets::PRESETS.iter().map(|preset| preset.name);
+                    let names = names.collect::<Vec<_>>().join(", ");
+                    return Err(format!("unknown preset {}; try one of {}", name, names).into());
+                }
             }
         }
+    };
+    let iterations = opt.params.iterations.or(grammar.iterations).unwrap_or(1);
+    let mut s = grammar.system();
+    for warning in s.validate() {
+        eprintln!("warning: {}", warning);
     }
-
-    let scale = 1. / (line.last().unwrap().0);
-    line.iter().map(|(x, y)| (x * scale, y * scale)).collect()
-}
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut s = {
-        use Symbol::*;
-        ParametricLSystem::new(
-            vec![A],
-            hashmap! {
-                A => vec![B, Left, A, Left, B],
-                B => vec![A, Right, B, Right, A],
-            },
-        )
+    // context-sensitive rules need each whole generation in memory
+    let lines = match s.expand(iterations) {
+        Ok(expansion) => grammar.turtle().draw(expansion),
+        Err(_) => {
+            s.step_n(iterations);
+            grammar.turtle().draw(&s.state)
+        }
     };
+    let (min, max) = opt.params.page.area()?;
+    let lines = turtle::fit(&lines, min, max);
 
     let mut program = vec![
         HpglCommand::InitializePlotter,
         HpglCommand::SelectPen { pen: 1 },
     ];
 
-    let mut lines_by_iter: HashMap<i32, Vec<(f64, f64)>> = HashMap::new();
-    lines_by_iter.insert(0, gen_line(&s));
-
-    const ITERS: i32 = 9;
-    for i in 1..ITERS {
-        s.state = s.evolve();
-
-        // lines_by_iter.insert(i, gen_line(&s));
+    for line in &lines {
+        program.extend(hpgl::polyline(line.iter().map(|&(x, y)| Coordinate {
+            x: x as f32,
+            y: y as f32,
+        })));
     }
 
-    const DOTS_PER_UNIT: f64 = 7650.; //2000.0;
-    // let points = lines_by_iter.get(&6).unwrap();
-    let points = gen_line(&s);
-    program.push(HpglCommand::PlotAbsolute(
-        Coordinate {
-            x: (1000. + points[0].0 * DOTS_PER_UNIT) as f32,
-            y: (1000. + points[0].1 * DOTS_PER_UNIT) as f32,
+    if opt.preview {
+        let mut fg = Figure::new();
+        let ax = fg.axes2d();
+        for line in &lines {
+            ax.lines(line.iter().map(|pt| pt.0), line.iter().map(|pt| pt.1), &[]);
         }
-        .into(),
-    ));
-    program.push(HpglCommand::PenDown);
-    // todo: coordinatechain
-    for (x, y) in &points {
-        program.push(HpglCommand::PlotAbsolute(
-            Coordinate {
-                x: (1000. + x * DOTS_PER_UNIT) as f32,
-                y: (1000. + y * DOTS_PER_UNIT) as f32,
-            }
-            .into(),
-        ));
+        fg.show()?;
     }
-    program.push(HpglCommand::PenUp);
 
-    let mut fg = Figure::new();
-    let mut ax = fg.axes2d();
-    ax.lines(
-        points.iter().map(|pt| pt.0),
-        points.iter().map(|pt| pt.1),
-        &[],
-    );
-    fg.show().unwrap();
-
-    println!("{:#?}", program);
-    let mut sp = serialport::open_with_settings(
-        "/dev/ttyUSB0",
-        &SerialPortSettings {
-            baud_rate: 9600,
-            data_bits: DataBits::Eight,
-            stop_bits: StopBits::One,
-            parity: Parity::None,
-            flow_control: FlowControl::Software,
-            timeout: Duration::from_secs(20),
-        },
-    )
-    .expect("can't open serial port");
-    DeviceControlInstruction::SetPlotterConfig(Default::default()).write(&mut sp)?;
-    DeviceControlInstruction::SetHandshakeMode(
-        HandshakeMode::Mode2,
-        HandshakeConfig::XonXoff {
-            xoff_threshold: 80,
-            xon_trigger_chars: b"\x11".to_vec(),
-        },
-    )
-    .write(&mut sp)?;
-    DeviceControlInstruction::SetExtHandshakeOptions {
-        interchar_delay: None,
-        xoff_trigger_chars: b"\x13".to_vec(),
-    }
-    .write(&mut sp)?;
     let program: HpglProgram = program.into();
-    program.write(&mut sp)?;
+    opt.output
+        .emit("03-sierpinski-arrowhead", &opt.params, None, &program)?;
     Ok(())
 }
@@ -0,0 +1,92 @@
+use hpgl::{Coordinate, HpglCommand, HpglProgram};
+use lsystem::turtle;
+use plotterart::output::OutputOptions;
+use plotterart::page::PageOptions;
+use serde::{Serialize, Serializer};
+use spacefill::Curve;
+use std::fmt::Display;
+use structopt::StructOpt;
+
+// four million points is already far finer than a pen can draw on a page
+const MAX_POINTS: u64 = 1 << 22;
+
+fn display<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+#[derive(Debug, Serialize, StructOpt)]
+struct Params {
+    /// Curve to draw: hilbert, moore, peano or zorder
+    #[structopt(long, default_value = "hilbert")]
+    #[serde(serialize_with = "display")]
+    curve: Curve,
+    /// Order of the curve; the grid has 2^order (3^order for peano) cells a side
+    #[structopt(long, default_value = "5")]
+    order: u32,
+    #[structopt(flatten)]
+    page: PageOptions,
+}
+
+#[derive(StructOpt)]
+#[structopt(name = "04-spacefill", about = "Space-filling curves")]
+struct Opt {
+    #[structopt(flatten)]
+    params: Params,
+    #[structopt(flatten)]
+    output: OutputOptions,
+}
+
+fn gen_program(params: &Params) -> Result<HpglProgram, Box<dyn std::error::Error>> {
+    let mut program = vec![
+        HpglCommand::InitializePlotter,
+        HpglCommand::SelectPen { pen: 1 },
+    ];
+
+    let (min, max) = params.page.area()?;
+    let lines = turtle::fit(&[params.curve.polyline(params.order)], min, max);
+    for line in &lines {
+        program.extend(hpgl::polyline(line.iter().map(|&(x, y)| Coordinate {
+            x: x as f32,
+            y: y as f32,
+        })));
+    }
+
+    Ok(program.into())
+}
+
+/// Checks the curve can be built at all, and in a reasonable amount of memory.
+fn check_order(curve: Curve, order: u32) -> Result<(), String> {
+    if order > curve.max_order() {
+        return Err(format!(
+            "{} curves only go up to order {}",
+            curve,
+            curve.max_order()
+        ));
+    }
+    if curve.len(order) > MAX_POINTS {
+        let highest = (0..order)
+            .rev()
+            .find(|&o| curve.len(o) <= MAX_POINTS)
+            .unwrap_or(0);
+        return Err(format!(
+            "an order {} {} curve has {} points; order {} is as fine as is worth plotting",
+            order,
+            curve,
+            curve.len(order),
+            highest
+        ));
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+
+    check_order(opt.params.curve, opt.params.order)?;
+    let program = gen_program(&opt.params)?;
+    opt.output
+        .emit("04-spacefill", &opt.params, None, &program)?;
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use hpgl::{Coordinate, HpglCommand, HpglProgram};
+use plotterart::output::OutputOptions;
+use plotterart::page::PageOptions;
+use plotterart::raster::Raster;
+use plotterart::sample;
+use plotterart::seed::{self, SeedOptions};
+use plotterart::stipple::{Mark, Relaxation};
+use serde::Serialize;
+use spacefill::Curve;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// A contrast, which has to be finite and above zero.
+fn gamma(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(v) if v.is_finite() && v > 0. => Ok(v),
+        Ok(_) => Err("must be finite and above zero".to_string()),
+        Err(e) => Err(format!("invalid gamma {:?}: {}", s, e)),
+    }
+}
+
+#[derive(Debug, Serialize, StructOpt)]
+struct Params {
+    /// PNG or PGM image to stipple
+    #[structopt(parse(from_os_str))]
+    image: PathBuf,
+    /// Number of dots
+    #[structopt(long, default_value = "2000")]
+    points: usize,
+    /// Rounds of Lloyd relaxation; more spreads the dots more evenly
+    #[structopt(long, default_value = "30")]
+    iterations: usize,
+    /// Contrast: above 1 clears dots out of the light tones
+    #[structopt(long, default_value = "1", parse(try_from_str = gamma))]
+    gamma: f64,
+    /// How to draw each dot: circle or dash
+    #[structopt(long, default_value = "circle")]
+    mark: Mark,
+    /// Size of each dot, in plotter units
+    #[structopt(long, default_value = "20")]
+    size: f64,
+    #[structopt(flatten)]
+    page: PageOptions,
+}
+
+#[derive(StructOpt)]
+#[structopt(name = "05-stipple", about = "Weighted Voronoi stippling of an image")]
+struct Opt {
+    #[structopt(flatten)]
+    params: Params,
+    #[structopt(flatten)]
+    seed: SeedOptions,
+    #[structopt(flatten)]
+    output: OutputOptions,
+}
+
+fn gen_program(
+    params: &Params,
+    raster: &Raster,
+    seed: u64,
+) -> Result<HpglProgram, Box<dyn std::error::Error>> {
+    let mut program = vec![
+        HpglCommand::InitializePlotter,
+        HpglCommand::SelectPen { pen: 1 },
+    ];
+
+    let area = params.page.area()?;
+    let points = sample::weighted(&mut seed::rng(seed), params.points, raster, area);
+    let mut points =
+        Relaxation::weighted(raster, area, params.gamma)?.relax(points, params.iterations);
+
+    // along a hilbert curve, so the pen hops between neighbouring dots
+    let ((x0, y0), (x1, y1)) = area;
+    let key =
+        |&(x, y): &(f64, f64)| Curve::Hilbert.key(10, ((x - x0) / (x1 - x0), (y - y0) / (y1 - y0)));
+    points.sort_by_key(key);
+
+    for point in points {
+        let mark = params.mark.draw(point, params.size);
+        program.extend(hpgl::polyline(mark.into_iter().map(|(x, y)| Coordinate {
+            x: x as f32,
+            y: y as f32,
+        })));
+    }
+
+    Ok(program.into())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+
+    let seed = opt.seed.resolve();
+    let raster = Raster::open(&opt.params.image)?;
+    let program = gen_program(&opt.params, &raster, seed)?;
+    opt.output
+        .emit("05-stipple", &opt.params, Some(seed), &program)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hpgl::PlotterWriteable;
+    use plotterart::page::Paper;
+
+    fn hpgl(seed: u64) -> Vec<u8> {
+        let params = Params {
+            image: PathBuf::new(),
+            points: 50,
+            iterations: 5,
+            gamma: 1.,
+            mark: Mark::Dash,
+            size: 20.,
+            page: PageOptions {
+                paper: Paper::Us,
+                margin: 500.,
+            },
+        };
+        // black on the left, fading to white on the right
+        let pixels = (0..64).map(|i| (i % 8) as f64 / 7.).collect();
+        let raster = Raster::new(8, 8, pixels);
+        let mut buf: Vec<u8> = Vec::new();
+        gen_program(&params, &raster, seed)
+            .unwrap()
+            .write(&mut buf)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn same_seed_same_plot() {
+        assert_eq!(hpgl(1234), hpgl(1234));
+        assert_ne!(hpgl(1234), hpgl(4321));
+    }
+}
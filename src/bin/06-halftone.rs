@@ -0,0 +1,147 @@
+use hpgl::{Coordinate, HpglCommand, HpglProgram};
+use plotterart::halftone::{self, Style, Tone};
+use plotterart::output::OutputOptions;
+use plotterart::page::PageOptions;
+use plotterart::raster::Raster;
+use plotterart::seed::{self, SeedOptions};
+use serde::Serialize;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// A length or contrast, which has to be finite and above zero.
+fn positive(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(v) if v.is_finite() && v > 0. => Ok(v),
+        Ok(_) => Err("must be finite and above zero".to_string()),
+        Err(e) => Err(format!("invalid number {:?}: {}", s, e)),
+    }
+}
+
+#[derive(Debug, Serialize, StructOpt)]
+struct Params {
+    /// PNG or PGM image to draw
+    #[structopt(parse(from_os_str))]
+    image: PathBuf,
+    /// How to draw it: lines (sine waves), spiral, crosshatch or tsp (one line through stipples)
+    #[structopt(long, default_value = "lines")]
+    style: Style,
+    /// Distance between lines, spiral turns or hatching, in plotter units
+    #[structopt(long, default_value = "60", parse(try_from_str = positive))]
+    spacing: f64,
+    /// Wavelength of the sine lines and spiral wiggle, in plotter units
+    #[structopt(long, default_value = "30", parse(try_from_str = positive))]
+    period: f64,
+    /// Number of hatching layers, from 1 to 4
+    #[structopt(long, default_value = "4")]
+    layers: usize,
+    /// Number of stipples for tsp
+    #[structopt(long, default_value = "3000")]
+    points: usize,
+    /// Rounds of Lloyd relaxation for tsp
+    #[structopt(long, default_value = "20")]
+    iterations: usize,
+    /// Contrast: above 1 lightens the midtones
+    #[structopt(long, default_value = "1", parse(try_from_str = positive))]
+    gamma: f64,
+    #[structopt(flatten)]
+    page: PageOptions,
+}
+
+#[derive(StructOpt)]
+#[structopt(name = "06-halftone", about = "Images drawn as lines")]
+struct Opt {
+    #[structopt(flatten)]
+    params: Params,
+    #[structopt(flatten)]
+    seed: SeedOptions,
+    #[structopt(flatten)]
+    output: OutputOptions,
+}
+
+fn gen_program(
+    params: &Params,
+    raster: &Raster,
+    seed: u64,
+) -> Result<HpglProgram, Box<dyn std::error::Error>> {
+    let mut program = vec![
+        HpglCommand::InitializePlotter,
+        HpglCommand::SelectPen { pen: 1 },
+    ];
+
+    let tone = Tone::new(raster, params.page.area()?, params.gamma)?;
+    let lines = match params.style {
+        Style::Lines => halftone::sine_lines(&tone, params.spacing, params.period),
+        Style::Spiral => vec![halftone::spiral(&tone, params.spacing, params.period)],
+        Style::Crosshatch => halftone::crosshatch(&tone, params.spacing, params.layers),
+        Style::Tsp => vec![halftone::tsp(
+            &mut seed::rng(seed),
+            &tone,
+            params.points,
+            params.iterations,
+        )?],
+    };
+
+    for line in lines {
+        program.extend(hpgl::polyline(line.into_iter().map(|(x, y)| Coordinate {
+            x: x as f32,
+            y: y as f32,
+        })));
+    }
+
+    Ok(program.into())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+
+    // only tsp is random, so only it needs (and reports) a seed
+    let seed = match opt.params.style {
+        Style::Tsp => Some(opt.seed.resolve()),
+        _ => None,
+    };
+    let raster = Raster::open(&opt.params.image)?;
+    let program = gen_program(&opt.params, &raster, seed.unwrap_or(0))?;
+    opt.output
+        .emit("06-halftone", &opt.params, seed, &program)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hpgl::PlotterWriteable;
+    use plotterart::page::Paper;
+
+    fn hpgl(seed: u64) -> Vec<u8> {
+        let params = Params {
+            image: PathBuf::new(),
+            style: Style::Tsp,
+            spacing: 60.,
+            period: 30.,
+            layers: 4,
+            points: 50,
+            iterations: 5,
+            gamma: 1.,
+            page: PageOptions {
+                paper: Paper::Us,
+                margin: 500.,
+            },
+        };
+        // black on the left, fading to white on the right
+        let pixels = (0..64).map(|i| (i % 8) as f64 / 7.).collect();
+        let raster = Raster::new(8, 8, pixels);
+        let mut buf: Vec<u8> = Vec::new();
+        gen_program(&params, &raster, seed)
+            .unwrap()
+            .write(&mut buf)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn same_seed_same_plot() {
+        assert_eq!(hpgl(1234), hpgl(1234));
+        assert_ne!(hpgl(1234), hpgl(4321));
+    }
+}
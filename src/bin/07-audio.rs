@@ -0,0 +1,214 @@
+use hpgl::{Coordinate, HpglCommand, HpglProgram};
+use plotterart::audio::{self, Audio};
+use plotterart::output::OutputOptions;
+use plotterart::page::PageOptions;
+use plotterart::ridge;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Style {
+    /// Stacked spectra, earliest at the back
+    Ridges,
+    /// Loudness round concentric rings, earliest in the middle
+    Rings,
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ridges" => Ok(Style::Ridges),
+            "rings" => Ok(Style::Rings),
+            _ => Err(format!("unknown style {:?} (expected ridges or rings)", s)),
+        }
+    }
+}
+
+/// An FFT window, which needs at least two samples to find any frequency in.
+fn window(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(window) if window >= 2 => Ok(window),
+        Ok(_) => Err("must be at least 2 samples".to_string()),
+        Err(e) => Err(format!("invalid window {:?}: {}", s, e)),
+    }
+}
+
+/// A dynamic range, which has to be above zero to scale decibels by.
+fn range(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(range) if range > 0. => Ok(range),
+        Ok(_) => Err("must be above zero".to_string()),
+        Err(e) => Err(format!("invalid range {:?}: {}", s, e)),
+    }
+}
+
+/// A number of ridges or rings, which has to be at least one.
+fn rows(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(rows) if rows >= 1 => Ok(rows),
+        Ok(_) => Err("must be at least 1".to_string()),
+        Err(e) => Err(format!("invalid number of rows {:?}: {}", s, e)),
+    }
+}
+
+/// A peak height, which has to be finite and above zero to leave the rows any room.
+fn lift(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(lift) if lift.is_finite() && lift > 0. => Ok(lift),
+        Ok(_) => Err("must be finite and above zero".to_string()),
+        Err(e) => Err(format!("invalid lift {:?}: {}", s, e)),
+    }
+}
+
+/// A fraction of the outermost ring, from 0 up to but not including 1.
+fn inner(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(inner) if (0. ..1.).contains(&inner) => Ok(inner),
+        Ok(_) => Err("must be at least 0 and below 1".to_string()),
+        Err(e) => Err(format!("invalid radius {:?}: {}", s, e)),
+    }
+}
+
+#[derive(Debug, Serialize, StructOpt)]
+struct Params {
+    /// FLAC file to draw
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+    /// What to draw: ridges (a spectrogram) or rings (the waveform)
+    #[structopt(long, default_value = "ridges")]
+    style: Style,
+    /// Where to start in the recording, in seconds
+    #[structopt(long, default_value = "0")]
+    start: f64,
+    /// Where to stop in the recording, in seconds; defaults to the end
+    #[structopt(long)]
+    end: Option<f64>,
+    /// Lowest frequency drawn by ridges, in Hz
+    #[structopt(long, default_value = "20")]
+    low: f64,
+    /// Highest frequency drawn by ridges, in Hz
+    #[structopt(long, default_value = "8000")]
+    high: f64,
+    /// Number of ridges or rings
+    #[structopt(long, default_value = "60", parse(try_from_str = rows))]
+    rows: usize,
+    /// Points along each ridge or ring
+    #[structopt(long, default_value = "200")]
+    grains: usize,
+    /// FFT window length in samples, at least 2
+    #[structopt(long, default_value = "2048", parse(try_from_str = window))]
+    window: usize,
+    /// Dynamic range drawn by ridges, in dB below the loudest peak
+    #[structopt(long, default_value = "60", parse(try_from_str = range))]
+    range: f32,
+    /// Height of the tallest peak, in gaps between rows
+    #[structopt(long, default_value = "4", parse(try_from_str = lift))]
+    lift: f64,
+    /// Radius of the innermost ring, as a fraction of the outermost
+    #[structopt(long, default_value = "0.2", parse(try_from_str = inner))]
+    inner: f64,
+    #[structopt(flatten)]
+    page: PageOptions,
+}
+
+#[derive(StructOpt)]
+#[structopt(
+    name = "07-audio",
+    about = "Spectrogram ridge lines or waveform rings of a recording"
+)]
+struct Opt {
+    #[structopt(flatten)]
+    params: Params,
+    #[structopt(flatten)]
+    output: OutputOptions,
+}
+
+/// Values in `0.0..=1.0` for each row of the plot.
+fn gen_rows(params: &Params, audio: &Audio) -> Vec<Vec<f64>> {
+    let clip = audio.clip(params.start, params.end);
+    let rows: Vec<Vec<f32>> = match params.style {
+        Style::Ridges => {
+            let bins = audio.bins(params.window, params.low, params.high);
+            let spectra = audio::spectrogram(clip, params.window, params.rows);
+            let loudest = spectra
+                .iter()
+                .flat_map(|spectrum| &spectrum[bins.clone()])
+                .fold(f32::MIN_POSITIVE, |max, &m| max.max(m));
+            spectra
+                .iter()
+                .map(|spectrum| {
+                    let levels = spectrum[bins.clone()]
+                        .iter()
+                        .map(|&m| audio::decibels(m, loudest, params.range))
+                        .collect::<Vec<_>>();
+                    audio::resample(&levels, params.grains)
+                })
+                .collect()
+        }
+        Style::Rings => {
+            let envelopes = (0..params.rows)
+                .map(|row| {
+                    let from = row * clip.len() / params.rows;
+                    let to = (row + 1) * clip.len() / params.rows;
+                    audio::envelope(&clip[from..to], params.grains)
+                })
+                .collect::<Vec<_>>();
+            let loudest = envelopes
+                .iter()
+                .flatten()
+                .fold(f32::MIN_POSITIVE, |max, &m| max.max(m));
+            envelopes
+                .into_iter()
+                .map(|envelope| envelope.into_iter().map(|m| m / loudest).collect())
+                .collect()
+        }
+    };
+
+    rows.into_iter()
+        .map(|row| row.into_iter().map(f64::from).collect())
+        .collect()
+}
+
+fn gen_program(params: &Params, audio: &Audio) -> Result<HpglProgram, Box<dyn std::error::Error>> {
+    let mut program = vec![
+        HpglCommand::InitializePlotter,
+        HpglCommand::SelectPen { pen: 1 },
+    ];
+
+    let rows = gen_rows(params, audio);
+    let area = params.page.area()?;
+    let lines = match params.style {
+        Style::Ridges => ridge::ridges(&rows, area, params.lift),
+        Style::Rings => {
+            let ((x0, y0), (x1, y1)) = area;
+            let outer = (x1 - x0).min(y1 - y0) / 2.;
+            let centre = ((x0 + x1) / 2., (y0 + y1) / 2.);
+            ridge::rings(&rows, centre, params.inner * outer, outer, params.lift)
+        }
+    };
+
+    for line in lines {
+        program.extend(hpgl::polyline(line.into_iter().map(|(x, y)| Coordinate {
+            x: x as f32,
+            y: y as f32,
+        })));
+    }
+
+    Ok(program.into())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+
+    let audio = Audio::open(&opt.params.file)?;
+    eprintln!("{:.1}s at {} Hz", audio.duration(), audio.sample_rate);
+    let program = gen_program(&opt.params, &audio)?;
+    opt.output.emit("07-audio", &opt.params, None, &program)?;
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use gnuplot::{Color, Figure};
+use hpgl::estimate::PlotterModel;
+use hpgl::optimize::{self, optimize};
+use hpgl::sender::{Checkpoint, Sender};
+use hpgl::simulator::PlotterState;
+use hpgl::stats::Stats;
+use hpgl::{HpglProgram, PlotterWriteable};
+use plotterart::progress;
+use plotterart::serial::{self, PortOptions};
+use spacefill::Curve;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+#[structopt(name = "plot", about = "Sends, inspects and tidies up HP-GL programs")]
+enum Command {
+    /// Stream a program to the plotter
+    Send {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        #[structopt(flatten)]
+        port: PortOptions,
+        /// Pick up from the checkpoint left by an interrupted send
+        #[structopt(long)]
+        resume: bool,
+        /// Skip the first N commands, restoring the plotter state they'd have left behind
+        #[structopt(long, conflicts_with = "resume")]
+        from: Option<usize>,
+    },
+    /// Draw a program on screen
+    Preview {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        /// Also draw pen-up travel
+        #[structopt(long)]
+        travel: bool,
+    },
+    /// Print drawing statistics and an estimated plot time
+    Stats {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        /// Baud rate to estimate plot time at
+        #[structopt(long, default_value = "9600", parse(try_from_str = serial::parse_baud))]
+        baud: u32,
+    },
+    /// Reorder strokes to minimize pen-up travel
+    Optimize {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        /// Where to write the optimized program; stdout if omitted
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+        /// Stroke order: nearest (greedy nearest neighbour), or a space-filling curve to sort
+        /// along (hilbert, moore, peano, zorder), which is much faster for big programs
+        #[structopt(long, default_value = "nearest")]
+        order: Order,
+    },
+}
+
+enum Order {
+    Nearest,
+    Curve(Curve),
+}
+
+impl FromStr for Order {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(Order::Nearest),
+            _ => s.parse().map(Order::Curve),
+        }
+    }
+}
+
+/// Order of the curve used to sort strokes: a grid of a thousand or so cells a side is finer than
+/// the pen.
+fn curve_order(curve: Curve) -> u32 {
+    match curve {
+        Curve::Peano => 7,
+        _ => 10,
+    }
+}
+
+fn load(file: &Path) -> Result<HpglProgram, Box<dyn Error>> {
+    Ok(fs::read_to_string(file)?.parse()?)
+}
+
+fn checkpoint_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".checkpoint");
+    path.into()
+}
+
+fn send(
+    file: &Path,
+    port: &PortOptions,
+    resume: bool,
+    from: Option<usize>,
+) -> Result<(), Box<dyn Error>> {
+    let program = load(file)?;
+    let mut sender = Sender::new(&program)
+        .checkpoint(Checkpoint::new(checkpoint_path(file)))
+        .model(PlotterModel::hp7470a(port.baud))
+        .on_progress(progress::bar);
+    if resume {
+        sender = sender.resume_from_checkpoint()?;
+    }
+    if let Some(from) = from {
+        sender = sender.resume_from(from);
+    }
+
+    let mut sp = port.open()?;
+    sender.send(&mut sp)?;
+
+    Ok(())
+}
+
+fn preview(file: &Path, travel: bool) -> Result<(), Box<dyn Error>> {
+    let program = load(file)?;
+
+    let mut fg = Figure::new();
+    let ax = fg.axes2d();
+    for stroke in optimize::strokes(&program) {
+        ax.lines(
+            stroke.points.iter().map(|pt| pt.x),
+            stroke.points.iter().map(|pt| pt.y),
+            &[Color("black")],
+        );
+    }
+
+    if travel {
+        let mut state = PlotterState::default();
+        for command in program.commands() {
+            for m in state.apply(command).iter().filter(|m| !m.pen_down) {
+                ax.lines(
+                    &[m.from.x, m.to.x],
+                    &[m.from.y, m.to.y],
+                    &[Color("light-gray")],
+                );
+            }
+        }
+    }
+
+    fg.show()?;
+
+    Ok(())
+}
+
+fn stats(file: &Path, baud: u32) -> Result<(), Box<dyn Error>> {
+    let program = load(file)?;
+
+    let eta = PlotterModel::hp7470a(baud).estimate(&program).as_secs();
+    println!("{}", Stats::of(&program));
+    println!("estimated plot time: {}m{:02}s", eta / 60, eta % 60);
+
+    Ok(())
+}
+
+fn optimize_file(file: &Path, output: Option<&Path>, order: &Order) -> Result<(), Box<dyn Error>> {
+    let program = load(file)?;
+    let before = Stats::of(&program);
+
+    let optimized = match (order, &before.bounds) {
+        (Order::Curve(curve), Some(bounds)) => {
+            let (width, height) = (bounds.width().max(1.), bounds.height().max(1.));
+            let sorted = optimize::spatial_sort(optimize::strokes(&program), |c| {
+                let x = ((c.x - bounds.min.x) / width) as f64;
+                let y = ((c.y - bounds.min.y) / height) as f64;
+                curve.key(curve_order(*curve), (x, y))
+            });
+            optimize::program(&optimize::merge(sorted))
+        }
+        _ => optimize(&program),
+    };
+
+    let after = Stats::of(&optimized);
+    eprintln!(
+        "pen-up travel: {:.0} -> {:.0}, pen lifts: {} -> {}",
+        before.pen_up_travel, after.pen_up_travel, before.pen_lifts, after.pen_lifts,
+    );
+
+    match output {
+        Some(output) => optimized.write(&mut fs::File::create(output)?)?,
+        None => optimized.write(&mut io::stdout())?,
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    match Command::from_args() {
+        Command::Send {
+            file,
+            port,
+            resume,
+            from,
+        } => send(&file, &port, resume, from),
+        Command::Preview { file, travel } => preview(&file, travel),
+        Command::Stats { file, baud } => stats(&file, baud),
+        Command::Optimize {
+            file,
+            output,
+            order,
+        } => optimize_file(&file, output.as_deref(), &order),
+    }
+}
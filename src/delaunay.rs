@@ -0,0 +1,455 @@
+//! Drawing toolkit over a Delaunay triangulation: the triangulation itself, its dual Voronoi
+//! diagram, circumcircles, convex hull, and the proximity graphs that are subgraphs of it
+//! (Gabriel graph, relative neighbourhood graph, Euclidean minimum spanning tree).
+//!
+//! Everything comes out as polylines, with each edge drawn exactly once.
+
+use crate::sample::Area;
+use delaunator::{triangulate, Point, Triangulation, EMPTY};
+use std::f64::consts::PI;
+use std::mem;
+
+pub type Polyline = Vec<(f64, f64)>;
+
+/// An undirected edge between two points, smaller index first.
+pub type Edge = (usize, usize);
+
+fn next_halfedge(e: usize) -> usize {
+    match e % 3 {
+        2 => e - 2,
+        _ => e + 1,
+    }
+}
+
+fn prev_halfedge(e: usize) -> usize {
+    match e % 3 {
+        0 => e + 2,
+        _ => e - 1,
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+pub struct Diagram {
+    pub points: Vec<(f64, f64)>,
+    pub triangulation: Triangulation,
+}
+
+impl Diagram {
+    /// Triangulates `points`; `None` if they're all collinear (or there are fewer than three).
+    pub fn new(points: Vec<(f64, f64)>) -> Option<Self> {
+        let triangulation = triangulate(
+            &points
+                .iter()
+                .map(|&(x, y)| Point { x, y })
+                .collect::<Vec<_>>(),
+        )?;
+
+        Some(Self {
+            points,
+            triangulation,
+        })
+    }
+
+    fn start(&self, e: usize) -> usize {
+        self.triangulation.triangles[e]
+    }
+
+    fn end(&self, e: usize) -> usize {
+        self.triangulation.triangles[next_halfedge(e)]
+    }
+
+    /// Every edge of the triangulation, once each.
+    pub fn edges(&self) -> Vec<Edge> {
+        let halfedges = &self.triangulation.halfedges;
+        (0..halfedges.len())
+            .filter(|&e| halfedges[e] == EMPTY || e < halfedges[e])
+            .map(|e| {
+                let (a, b) = (self.start(e), self.end(e));
+                (a.min(b), a.max(b))
+            })
+            .collect()
+    }
+
+    /// Draws `edges` as two-point lines.
+    pub fn lines(&self, edges: &[Edge]) -> Vec<Polyline> {
+        edges
+            .iter()
+            .map(|&(a, b)| vec![self.points[a], self.points[b]])
+            .collect()
+    }
+
+    /// Each triangle as a closed outline. Shared edges get drawn twice; prefer
+    /// `lines(&edges())` for plotting.
+    pub fn triangles(&self) -> Vec<Polyline> {
+        self.triangulation
+            .triangles
+            .chunks(3)
+            .map(|t| {
+                vec![
+                    self.points[t[0]],
+                    self.points[t[1]],
+                    self.points[t[2]],
+                    self.points[t[0]],
+                ]
+            })
+            .collect()
+    }
+
+    /// The closed outline of the convex hull.
+    pub fn hull(&self) -> Polyline {
+        let hull = &self.triangulation.hull;
+        hull.iter()
+            .chain(hull.first())
+            .map(|&i| self.points[i])
+            .collect()
+    }
+
+    /// The centre and radius of triangle `t`'s circumcircle.
+    pub fn circumcircle(&self, t: usize) -> ((f64, f64), f64) {
+        let triangles = &self.triangulation.triangles;
+        let a = self.points[triangles[3 * t]];
+        let b = self.points[triangles[3 * t + 1]];
+        let c = self.points[triangles[3 * t + 2]];
+
+        let (bx, by) = (b.0 - a.0, b.1 - a.1);
+        let (cx, cy) = (c.0 - a.0, c.1 - a.1);
+        let (bl, cl) = (bx * bx + by * by, cx * cx + cy * cy);
+        let d = 0.5 / (bx * cy - by * cx);
+
+        let centre = (a.0 + (cy * bl - by * cl) * d, a.1 + (bx * cl - cx * bl) * d);
+        (centre, distance(centre, a))
+    }
+
+    /// Every triangle's circumcircle, as `segments`-sided polygons. Slivers on the hull have
+    /// enormous ones, so `clip` these before plotting.
+    pub fn circumcircles(&self, segments: usize) -> Vec<Polyline> {
+        (0..self.triangulation.triangles.len() / 3)
+            .map(|t| {
+                let (centre, radius) = self.circumcircle(t);
+                circle(centre, radius, segments)
+            })
+            .collect()
+    }
+
+    /// Edges of the Voronoi diagram between neighbouring circumcentres. The rays running off to
+    /// infinity from the hull are left out, but circumcentres of slivers on the hull can still
+    /// be far away, so `clip` these before plotting.
+    pub fn voronoi_edges(&self) -> Vec<Polyline> {
+        let halfedges = &self.triangulation.halfedges;
+        (0..halfedges.len())
+            .filter(|&e| halfedges[e] != EMPTY && e < halfedges[e])
+            .map(|e| {
+                vec![
+                    self.circumcircle(e / 3).0,
+                    self.circumcircle(halfedges[e] / 3).0,
+                ]
+            })
+            .collect()
+    }
+
+    /// The Voronoi cell around each point, as a closed polygon, or `None` for points on the hull,
+    /// whose cells are unbounded.
+    pub fn voronoi_cells(&self) -> Vec<Option<Polyline>> {
+        let halfedges = &self.triangulation.halfedges;
+
+        // a halfedge leading into each point
+        let mut incoming = vec![EMPTY; self.points.len()];
+        for e in 0..halfedges.len() {
+            incoming[self.end(e)] = e;
+        }
+
+        incoming
+            .into_iter()
+            .map(|start| {
+                if start == EMPTY {
+                    return None;
+                }
+
+                let mut cell = Vec::new();
+                let mut e = start;
+                loop {
+                    cell.push(self.circumcircle(e / 3).0);
+                    e = halfedges[next_halfedge(e)];
+                    if e == EMPTY {
+                        return None;
+                    }
+                    if e == start {
+                        break;
+                    }
+                }
+                cell.push(cell[0]);
+
+                Some(cell)
+            })
+            .collect()
+    }
+
+    /// Edges whose diametral circle is empty: no other point sees them at a right angle or more.
+    pub fn gabriel_graph(&self) -> Vec<Edge> {
+        let halfedges = &self.triangulation.halfedges;
+        // the diametral circle can only be broken by the vertices opposite the edge
+        let clear = |e: usize| {
+            let (a, b) = (self.points[self.start(e)], self.points[self.end(e)]);
+            let c = self.points[self.start(prev_halfedge(e))];
+            (a.0 - c.0) * (b.0 - c.0) + (a.1 - c.1) * (b.1 - c.1) > 0.
+        };
+
+        (0..halfedges.len())
+            .filter(|&e| halfedges[e] == EMPTY || e < halfedges[e])
+            .filter(|&e| clear(e) && (halfedges[e] == EMPTY || clear(halfedges[e])))
+            .map(|e| {
+                let (a, b) = (self.start(e), self.end(e));
+                (a.min(b), a.max(b))
+            })
+            .collect()
+    }
+
+    /// Edges with no other point closer to both ends than they are to each other. Checks every
+    /// point against every Gabriel edge, so it's quadratic-ish; fine for plotting-sized inputs.
+    pub fn relative_neighbourhood_graph(&self) -> Vec<Edge> {
+        self.gabriel_graph()
+            .into_iter()
+            .filter(|&(a, b)| {
+                let (pa, pb) = (self.points[a], self.points[b]);
+                let length = distance(pa, pb);
+                !self.points.iter().enumerate().any(|(c, &pc)| {
+                    c != a && c != b && distance(pa, pc) < length && distance(pb, pc) < length
+                })
+            })
+            .collect()
+    }
+
+    /// The Euclidean minimum spanning tree, by Kruskal's algorithm over the triangulation's edges.
+    pub fn minimum_spanning_tree(&self) -> Vec<Edge> {
+        let mut edges = self.edges();
+        edges.sort_by(|&(a, b), &(c, d)| {
+            let ab = distance(self.points[a], self.points[b]);
+            let cd = distance(self.points[c], self.points[d]);
+            ab.partial_cmp(&cd).unwrap()
+        });
+
+        let mut parent = (0..self.points.len()).collect::<Vec<_>>();
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        edges
+            .into_iter()
+            .filter(|&(a, b)| {
+                let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+                parent[ra] = rb;
+                ra != rb
+            })
+            .collect()
+    }
+}
+
+/// A closed `segments`-sided polygon approximating a circle.
+pub fn circle(centre: (f64, f64), radius: f64, segments: usize) -> Polyline {
+    (0..=segments)
+        .map(|i| {
+            let theta = 2. * PI * (i % segments) as f64 / segments as f64;
+            (
+                centre.0 + radius * theta.cos(),
+                centre.1 + radius * theta.sin(),
+            )
+        })
+        .collect()
+}
+
+/// The stretch `t0..=t1` (as fractions of the way from `a` to `b`) of a segment that's inside
+/// `area`, by Liang-Barsky; `None` if it misses entirely.
+fn clip_segment(a: (f64, f64), b: (f64, f64), area: Area) -> Option<(f64, f64)> {
+    if ![a.0, a.1, b.0, b.1].iter().all(|v| v.is_finite()) {
+        return None;
+    }
+
+    let ((x0, y0), (x1, y1)) = area;
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0f64, 1f64);
+    // each edge of the area as how fast the segment heads out through it, and how far it has to go
+    for &(p, q) in &[
+        (-dx, a.0 - x0),
+        (dx, x1 - a.0),
+        (-dy, a.1 - y0),
+        (dy, y1 - a.1),
+    ] {
+        if p == 0. {
+            if q < 0. {
+                return None;
+            }
+        } else if p < 0. {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+
+    if t0 <= t1 {
+        Some((t0, t1))
+    } else {
+        None
+    }
+}
+
+/// The pieces of `line` inside `area`, cut where it leaves and comes back.
+pub fn clip(line: &[(f64, f64)], area: Area) -> Vec<Polyline> {
+    let mut pieces = Vec::new();
+    let mut piece = Vec::new();
+    for segment in line.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        // the ends exactly, so that pieces join up
+        let at = |t: f64| {
+            if t == 0. {
+                a
+            } else if t == 1. {
+                b
+            } else {
+                (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))
+            }
+        };
+
+        match clip_segment(a, b, area) {
+            Some((t0, t1)) => {
+                if t0 > 0. || piece.is_empty() {
+                    pieces.push(mem::take(&mut piece));
+                    piece.push(at(t0));
+                }
+                piece.push(at(t1));
+                if t1 < 1. {
+                    pieces.push(mem::take(&mut piece));
+                }
+            }
+            None => pieces.push(mem::take(&mut piece)),
+        }
+    }
+    pieces.push(piece);
+    pieces.retain(|piece| piece.len() > 1);
+
+    pieces
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// a 3x3 grid, with the middle of each side nudged outwards so that it's on the hull, and
+    /// jittered so no four points share a circumcircle.
+    fn grid() -> Diagram {
+        let points = (0..9)
+            .map(|i| {
+                let (x, y) = ((i % 3) as f64, (i / 3) as f64);
+                let bulge = if (x == 1.) != (y == 1.) { 1.02 } else { 1. };
+                (
+                    1. + (x - 1.) * bulge + 0.001 * i as f64,
+                    1. + (y - 1.) * bulge,
+                )
+            })
+            .collect();
+        Diagram::new(points).unwrap()
+    }
+
+    #[test]
+    fn edges_are_deduplicated() {
+        let diagram = grid();
+        let mut edges = diagram.edges();
+        // euler: 9 points, 8 on the hull, so 2 * 9 - 8 - 2 = 8 triangles and 9 + 8 - 1 edges
+        assert_eq!(diagram.triangles().len(), 8);
+        assert_eq!(edges.len(), 16);
+        edges.sort();
+        edges.dedup();
+        assert_eq!(edges.len(), 16);
+    }
+
+    #[test]
+    fn hull_is_closed() {
+        let hull = grid().hull();
+        assert_eq!(hull.len(), 9);
+        assert_eq!(hull.first(), hull.last());
+    }
+
+    #[test]
+    fn only_the_middle_cell_is_bounded() {
+        let diagram = grid();
+        let cells = diagram.voronoi_cells();
+        assert_eq!(cells.iter().filter(|cell| cell.is_some()).count(), 1);
+
+        let cell = cells[4].as_ref().unwrap();
+        assert_eq!(cell.first(), cell.last());
+        // its corners are near the centres of the four squares around it
+        for &(x, y) in cell {
+            assert!((x - 1.).abs() > 0.3 && (x - 1.).abs() < 0.7);
+            assert!((y - 1.).abs() > 0.3 && (y - 1.).abs() < 0.7);
+        }
+    }
+
+    #[test]
+    fn circumcircles_pass_through_corners() {
+        let diagram = grid();
+        let triangles = &diagram.triangulation.triangles;
+        for t in 0..triangles.len() / 3 {
+            let (centre, radius) = diagram.circumcircle(t);
+            for &i in &triangles[3 * t..3 * t + 3] {
+                assert!((distance(centre, diagram.points[i]) - radius).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn proximity_graphs_nest() {
+        let diagram = grid();
+        let delaunay = diagram.edges();
+        let gabriel = diagram.gabriel_graph();
+        let rng = diagram.relative_neighbourhood_graph();
+        let mst = diagram.minimum_spanning_tree();
+
+        assert!(gabriel.iter().all(|edge| delaunay.contains(edge)));
+        assert!(rng.iter().all(|edge| gabriel.contains(edge)));
+        assert!(mst.iter().all(|edge| rng.contains(edge)));
+
+        // the grid's diagonals are all dropped, leaving its 12 sides
+        assert_eq!(rng.len(), 12);
+        assert_eq!(mst.len(), 8);
+    }
+
+    #[test]
+    fn clipping_cuts_at_the_edges() {
+        let area = ((0., 0.), (10., 10.));
+        let line = [(-5., 5.), (5., 5.), (5., 15.), (8., 5.), (8., 2.)];
+        assert_eq!(
+            clip(&line, area),
+            &[
+                vec![(0., 5.), (5., 5.), (5., 10.)],
+                vec![(6.5, 10.), (8., 5.), (8., 2.)]
+            ]
+        );
+        assert!(clip(&[(-1., -1.), (-1., 11.)], area).is_empty());
+        assert!(clip(&[(f64::NAN, 1.), (1., 1.)], area).is_empty());
+    }
+
+    #[test]
+    fn slivers_on_the_hull_are_clipped() {
+        // the bottom triangle is nearly flat, so its circumcentre is far below the square
+        let points = vec![(0., 0.), (10., 0.), (10., 10.), (0., 10.), (5., 0.01)];
+        let diagram = Diagram::new(points).unwrap();
+        let area = ((0., 0.), (10., 10.));
+        let inside = |&(x, y): &(f64, f64)| (0. ..=10.).contains(&x) && (0. ..=10.).contains(&y);
+
+        for lines in &[diagram.voronoi_edges(), diagram.circumcircles(64)] {
+            assert!(lines.iter().flatten().any(|p| !inside(p)));
+            let clipped = lines
+                .iter()
+                .flat_map(|line| clip(line, area))
+                .collect::<Vec<_>>();
+            assert!(!clipped.is_empty());
+            assert!(clipped.iter().flatten().all(inside));
+        }
+    }
+}
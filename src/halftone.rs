@@ -0,0 +1,399 @@
+//! Renders a grayscale image as lines a pen can draw: sine waves that swell in the shadows, a
+//! spiral that wiggles, layers of crosshatching, or a single unbroken path through stipples.
+
+use crate::delaunay::Polyline;
+use crate::raster::Raster;
+use crate::sample::{self, Area};
+use crate::stipple::Relaxation;
+use rand::Rng;
+use serde::Serialize;
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+/// An image placed on the page, read as how much ink each point wants.
+pub struct Tone<'a> {
+    raster: &'a Raster,
+    area: Area,
+    gamma: f64,
+}
+
+impl<'a> Tone<'a> {
+    /// `raster` fitted into `area` with its aspect ratio kept. `gamma` above 1 lightens the
+    /// midtones, below 1 darkens them; it has to be finite and above zero.
+    pub fn new(raster: &'a Raster, area: Area, gamma: f64) -> Result<Self, String> {
+        if !(gamma.is_finite() && gamma > 0.) {
+            return Err(format!(
+                "gamma has to be finite and above zero, not {}",
+                gamma
+            ));
+        }
+
+        Ok(Self {
+            raster,
+            area: raster.fit(area.0, area.1),
+            gamma,
+        })
+    }
+
+    /// Where the image ended up.
+    pub fn area(&self) -> Area {
+        self.area
+    }
+
+    /// From `0.0` for white to `1.0` for black; nothing outside the image.
+    pub fn darkness(&self, (x, y): (f64, f64)) -> f64 {
+        let ((x0, y0), (x1, y1)) = self.area;
+        if x < x0 || x > x1 || y < y0 || y > y1 {
+            return 0.;
+        }
+        let brightness = self
+            .raster
+            .sample(((x - x0) / (x1 - x0), (y - y0) / (y1 - y0)));
+        (1. - brightness).powf(self.gamma)
+    }
+}
+
+// points per wave; plenty for the curve to look smooth at plotter resolution
+const STEPS_PER_PERIOD: f64 = 16.;
+
+/// Horizontal lines `spacing` apart, each a sine wave of wavelength `period` whose amplitude
+/// follows the darkness under it, up to touching its neighbours. Rows alternate direction, so
+/// the pen doesn't have to travel back across the page between them. Nothing unless `spacing`
+/// and `period` are both positive.
+pub fn sine_lines(tone: &Tone, spacing: f64, period: f64) -> Vec<Polyline> {
+    if !(spacing > 0. && period > 0.) {
+        return Vec::new();
+    }
+
+    let ((x0, y0), (x1, y1)) = tone.area();
+    let rows = ((y1 - y0) / spacing).floor().max(1.) as usize;
+    let steps = ((x1 - x0) / period * STEPS_PER_PERIOD).ceil().max(1.) as usize;
+
+    (0..rows)
+        .map(|row| {
+            let centre = y0 + (row as f64 + 0.5) * spacing;
+            let mut line = (0..=steps)
+                .map(|i| {
+                    let x = x0 + (x1 - x0) * i as f64 / steps as f64;
+                    let amplitude = 0.5 * spacing * tone.darkness((x, centre));
+                    (x, centre + amplitude * (2. * PI * (x - x0) / period).sin())
+                })
+                .collect::<Vec<_>>();
+            if row & 1 == 1 {
+                line.reverse();
+            }
+            line
+        })
+        .collect()
+}
+
+/// An Archimedean spiral out from the middle of the image, its turns `spacing` apart, wiggling
+/// in and out with wavelength `period` as far as the darkness says. Nothing unless `spacing`
+/// and `period` are both positive.
+pub fn spiral(tone: &Tone, spacing: f64, period: f64) -> Polyline {
+    if !(spacing > 0. && period > 0.) {
+        return Vec::new();
+    }
+
+    let ((x0, y0), (x1, y1)) = tone.area();
+    let centre = ((x0 + x1) / 2., (y0 + y1) / 2.);
+    let radius = (x1 - x0).min(y1 - y0) / 2.;
+    let step = period / STEPS_PER_PERIOD;
+
+    let mut line = Vec::new();
+    let (mut theta, mut phase) = (0f64, 0f64);
+    loop {
+        let r = spacing * theta / (2. * PI);
+        if r > radius - spacing / 2. {
+            break;
+        }
+
+        let (cos, sin) = (theta.cos(), theta.sin());
+        let darkness = tone.darkness((centre.0 + r * cos, centre.1 + r * sin));
+        let wiggle = 0.5 * spacing * darkness * phase.sin();
+        line.push((centre.0 + (r + wiggle) * cos, centre.1 + (r + wiggle) * sin));
+
+        // roughly even steps along the curve, except right at the middle
+        theta += step / r.max(spacing);
+        phase += 2. * PI * step / period;
+    }
+
+    line
+}
+
+/// Layers of parallel hatching `spacing` apart, each at its own angle and drawn only where the
+/// image is darker than that layer's threshold, so the darkest parts get every layer. Up to four
+/// layers: the diagonals, then horizontal, then vertical. Nothing unless `spacing` is positive.
+pub fn crosshatch(tone: &Tone, spacing: f64, layers: usize) -> Vec<Polyline> {
+    if spacing.is_nan() || spacing <= 0. {
+        return Vec::new();
+    }
+
+    const ANGLES: [f64; 4] = [45., -45., 0., 90.];
+    let layers = layers.min(ANGLES.len());
+
+    let ((x0, y0), (x1, y1)) = tone.area();
+    let corners = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)];
+    let step = spacing / 2.;
+
+    let mut lines = Vec::new();
+    for (layer, angle) in ANGLES.iter().take(layers).enumerate() {
+        let threshold = (layer + 1) as f64 / (layers + 1) as f64;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        // each hatch line runs along (cos, sin), at some offset along the normal (-sin, cos)
+        let along = |(x, y): (f64, f64)| x * cos + y * sin;
+        let across = |(x, y): (f64, f64)| y * cos - x * sin;
+        let extent = |f: &dyn Fn((f64, f64)) -> f64| {
+            let values = corners.iter().map(|&c| f(c));
+            let min = values.clone().fold(f64::INFINITY, f64::min);
+            let max = values.fold(f64::NEG_INFINITY, f64::max);
+            (min, max)
+        };
+        let (start, end) = extent(&along);
+        let (low, high) = extent(&across);
+
+        let count = ((high - low) / spacing).floor() as usize;
+        for i in 0..=count {
+            let offset = low + (i as f64 + 0.5) * spacing;
+            let point = |t: f64| (t * cos - offset * sin, t * sin + offset * cos);
+            let steps = ((end - start) / step).ceil() as usize;
+
+            // collect the stretches that are dark enough, as straight strokes
+            let mut run: Option<(f64, f64)> = None;
+            let mut runs = Vec::new();
+            for s in 0..=steps {
+                let t = start + s as f64 * step;
+                if tone.darkness(point(t)) > threshold {
+                    run = Some(match run {
+                        Some((from, _)) => (from, t),
+                        None => (t, t),
+                    });
+                } else if let Some(stretch) = run.take() {
+                    runs.push(stretch);
+                }
+            }
+            runs.extend(run);
+
+            // alternate directions, as for the sine lines
+            if i & 1 == 1 {
+                runs.reverse();
+            }
+            for (from, to) in runs.into_iter().filter(|&(from, to)| to > from) {
+                lines.push(match i & 1 {
+                    0 => vec![point(from), point(to)],
+                    _ => vec![point(to), point(from)],
+                });
+            }
+        }
+    }
+
+    lines
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+/// Orders `points` into a short path visiting each once: greedily to the nearest unvisited
+/// point, then uncrossed by 2-opt until no swap helps (or `passes` run out). Quadratic per
+/// pass, which is fine for a few thousand stipples.
+pub fn tour(mut points: Vec<(f64, f64)>, passes: usize) -> Polyline {
+    for i in 1..points.len() {
+        let last = points[i - 1];
+        let nearest = (i..points.len())
+            .min_by(|&a, &b| {
+                distance(last, points[a])
+                    .partial_cmp(&distance(last, points[b]))
+                    .unwrap()
+            })
+            .unwrap();
+        points.swap(i, nearest);
+    }
+
+    let n = points.len();
+    for _ in 0..passes {
+        let mut improved = false;
+        for i in 0..n.saturating_sub(2) {
+            for j in i + 2..n {
+                let (a, b, c) = (points[i], points[i + 1], points[j]);
+                // the path is open, so reversing all the way to the end only rejoins at one edge
+                let (before, after) = match points.get(j + 1) {
+                    Some(&d) => (
+                        distance(a, b) + distance(c, d),
+                        distance(a, c) + distance(b, d),
+                    ),
+                    None => (distance(a, b), distance(a, c)),
+                };
+                if after < before - 1e-9 {
+                    points[i + 1..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+
+    points
+}
+
+/// TSP art: `points` stipples, relaxed over `iterations` rounds of weighted Lloyd's algorithm,
+/// then joined into one continuous line.
+pub fn tsp<R: Rng>(
+    rng: &mut R,
+    tone: &Tone,
+    points: usize,
+    iterations: usize,
+) -> Result<Polyline, String> {
+    let area = tone.area();
+    let stipples = sample::weighted(rng, points, tone.raster, area);
+    let stipples = Relaxation::weighted(tone.raster, area, tone.gamma)?.relax(stipples, iterations);
+    Ok(tour(stipples, 20))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    Lines,
+    Spiral,
+    Crosshatch,
+    Tsp,
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lines" | "sine" => Ok(Style::Lines),
+            "spiral" => Ok(Style::Spiral),
+            "crosshatch" | "hatch" => Ok(Style::Crosshatch),
+            "tsp" => Ok(Style::Tsp),
+            _ => Err(format!(
+                "unknown style {:?} (expected lines, spiral, crosshatch or tsp)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::seed;
+
+    const AREA: Area = ((0., 0.), (100., 100.));
+
+    /// black on the left half, white on the right.
+    fn half() -> Raster {
+        let pixels = (0..100).map(|i| if i % 10 < 5 { 0. } else { 1. }).collect();
+        Raster::new(10, 10, pixels)
+    }
+
+    #[test]
+    fn sine_lines_flatten_in_the_light() {
+        let raster = half();
+        let tone = Tone::new(&raster, AREA, 1.).unwrap();
+        let lines = sine_lines(&tone, 10., 8.);
+        assert_eq!(lines.len(), 10);
+
+        for (row, line) in lines.iter().enumerate() {
+            let centre = 5. + 10. * row as f64;
+            let light = line.iter().filter(|&&(x, _)| x > 50.);
+            assert!(light.clone().count() > 0);
+            assert!(light.clone().all(|&(_, y)| (y - centre).abs() < 1e-9));
+            let swing = line
+                .iter()
+                .map(|&(_, y)| (y - centre).abs())
+                .fold(0., f64::max);
+            assert!(swing > 4.9 && swing <= 5. + 1e-9);
+        }
+        // second row runs backwards
+        assert!(lines[1][0].0 > lines[1][1].0);
+    }
+
+    #[test]
+    fn gamma_must_be_positive() {
+        let raster = half();
+        for &gamma in &[0., -1., f64::NAN, f64::INFINITY] {
+            assert!(Tone::new(&raster, AREA, gamma).is_err(), "{}", gamma);
+        }
+    }
+
+    #[test]
+    fn spiral_stays_in_the_circle() {
+        let raster = half();
+        let tone = Tone::new(&raster, AREA, 1.).unwrap();
+        let line = spiral(&tone, 5., 4.);
+        assert!(line.len() > 100);
+        assert!(line
+            .iter()
+            .all(|&point| distance(point, (50., 50.)) <= 50. + 1e-9));
+    }
+
+    #[test]
+    fn crosshatch_layers_follow_the_tone() {
+        let pixels = (0..100).map(|i| (i % 10) as f64 / 9.).collect();
+        let raster = Raster::new(10, 10, pixels);
+        let tone = Tone::new(&raster, AREA, 1.).unwrap();
+
+        // every stroke lies where at least the lightest layer's threshold is passed
+        for layers in 1..=4 {
+            let lines = crosshatch(&tone, 5., layers);
+            assert!(!lines.is_empty());
+            for line in &lines {
+                assert_eq!(line.len(), 2);
+                for &point in line {
+                    assert!(tone.darkness(point) > 1. / (layers + 1) as f64);
+                }
+            }
+        }
+        // one layer covers just the darker half
+        assert!(crosshatch(&tone, 5., 1)
+            .iter()
+            .all(|line| line.iter().all(|&(x, _)| x < 50.)));
+        assert!(crosshatch(&tone, 5., 4).len() > crosshatch(&tone, 5., 1).len());
+    }
+
+    #[test]
+    fn no_spacing_or_period_draws_nothing() {
+        let raster = half();
+        let tone = Tone::new(&raster, AREA, 1.).unwrap();
+        for &(spacing, period) in &[(0., 8.), (-10., 8.), (f64::NAN, 8.), (10., 0.), (10., -8.)] {
+            assert!(sine_lines(&tone, spacing, period).is_empty());
+            assert!(spiral(&tone, spacing, period).is_empty());
+        }
+        for &spacing in &[0., -5., f64::NAN] {
+            assert!(crosshatch(&tone, spacing, 4).is_empty());
+        }
+    }
+
+    #[test]
+    fn tour_visits_everything_without_crossing() {
+        let points = (0..30)
+            .map(|i| {
+                let theta = 2. * PI * (i * 7 % 30) as f64 / 30.;
+                (10. * theta.cos(), 10. * theta.sin())
+            })
+            .collect::<Vec<_>>();
+        let path = tour(points.clone(), 50);
+        assert_eq!(path.len(), points.len());
+        for point in &points {
+            assert!(path.contains(point));
+        }
+        // the best open path round a circle skips just one of its sides
+        let length: f64 = path.windows(2).map(|pair| distance(pair[0], pair[1])).sum();
+        let side = 20. * (PI / 30.).sin();
+        assert!(length < 29.5 * side, "{} vs {}", length, side);
+    }
+
+    #[test]
+    fn tsp_is_one_line_in_the_dark() {
+        let raster = half();
+        let tone = Tone::new(&raster, AREA, 1.).unwrap();
+        let line = tsp(&mut seed::rng(1), &tone, 50, 5).unwrap();
+        assert_eq!(line.len(), 50);
+        assert!(line.iter().all(|&(x, _)| x <= 50.));
+    }
+}
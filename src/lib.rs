@@ -0,0 +1,15 @@
+//! bits shared between the plotterart binaries
+
+pub mod audio;
+pub mod delaunay;
+pub mod halftone;
+pub mod output;
+pub mod page;
+pub mod partials;
+pub mod progress;
+pub mod raster;
+pub mod ridge;
+pub mod sample;
+pub mod seed;
+pub mod serial;
+pub mod stipple;
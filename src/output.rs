@@ -0,0 +1,93 @@
+use crate::progress;
+use crate::serial::PortOptions;
+use hpgl::estimate::PlotterModel;
+use hpgl::sender::Sender;
+use hpgl::stats::Stats;
+use hpgl::{HpglProgram, PlotterWriteable};
+use serde::Serialize;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// Where a generated program ends up: on paper, or saved for later.
+#[derive(Clone, Debug, StructOpt)]
+pub struct OutputOptions {
+    /// Save HPGL to this file, with parameters alongside in `<file>.json`, instead of plotting.
+    /// `-` writes HPGL to stdout, and the parameters to stderr
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: Option<PathBuf>,
+    #[structopt(flatten)]
+    pub port: PortOptions,
+}
+
+/// Everything needed to regenerate a saved program.
+#[derive(Serialize)]
+struct Metadata<'a, P> {
+    generator: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    params: &'a P,
+}
+
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".json");
+    sidecar.into()
+}
+
+impl OutputOptions {
+    /// Sends `program` to its destination, reporting statistics on stderr. `params` and the RNG
+    /// `seed`, for generators which use one, are recorded in the JSON sidecar when saving to a
+    /// file, or as a line of JSON on stderr when writing to stdout.
+    pub fn emit<P>(
+        &self,
+        generator: &str,
+        params: &P,
+        seed: Option<u64>,
+        program: &HpglProgram,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        P: Serialize,
+    {
+        let eta = PlotterModel::hp7470a(self.port.baud)
+            .estimate(program)
+            .as_secs();
+        eprintln!("{}", Stats::of(program));
+        eprintln!("estimated plot time: {}m{:02}s", eta / 60, eta % 60);
+
+        let metadata = Metadata {
+            generator,
+            seed,
+            params,
+        };
+        match &self.output {
+            Some(path) if path.as_os_str() == "-" => {
+                let stdout = io::stdout();
+                let mut stdout = stdout.lock();
+                program.write(&mut stdout)?;
+                stdout.flush()?;
+
+                // there's no file to put a sidecar next to, but piped plots still want recording
+                eprintln!("{}", serde_json::to_string(&metadata)?);
+            }
+            Some(path) => {
+                let mut file = BufWriter::new(File::create(path)?);
+                program.write(&mut file)?;
+                file.flush()?;
+
+                serde_json::to_writer_pretty(File::create(sidecar_path(path))?, &metadata)?;
+            }
+            None => {
+                let mut sp = self.port.open()?;
+                Sender::new(program)
+                    .model(PlotterModel::hp7470a(self.port.baud))
+                    .on_progress(progress::bar)
+                    .send(&mut sp)?;
+            }
+        }
+
+        Ok(())
+    }
+}
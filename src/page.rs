@@ -0,0 +1,90 @@
+use crate::sample::Area;
+use hpgl::Coordinate;
+use serde::Serialize;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Paper {
+    /// US letter
+    Us,
+    A4,
+}
+
+impl FromStr for Paper {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "us" | "letter" => Ok(Paper::Us),
+            "a4" => Ok(Paper::A4),
+            _ => Err(format!("unknown paper {:?} (expected us or a4)", s)),
+        }
+    }
+}
+
+impl Paper {
+    /// The plottable area's far corner, in plotter units.
+    pub fn size(self) -> (f64, f64) {
+        let width = match self {
+            Paper::Us => Coordinate::MAX_X_US,
+            Paper::A4 => Coordinate::MAX_X_A4,
+        };
+        (width as f64, Coordinate::MAX_Y as f64)
+    }
+}
+
+/// Which sheet is loaded and how much of it to leave blank.
+#[derive(Clone, Debug, Serialize, StructOpt)]
+pub struct PageOptions {
+    /// Paper size: us or a4
+    #[structopt(long, default_value = "us")]
+    pub paper: Paper,
+    /// Blank border around the page, in plotter units
+    #[structopt(long, default_value = "500")]
+    pub margin: f64,
+}
+
+impl PageOptions {
+    /// The drawable rectangle inside the margins, as `(min, max)` corners in plotter units. Fails
+    /// if the margin is negative or leaves nothing to draw on.
+    pub fn area(&self) -> Result<Area, String> {
+        let (width, height) = self.paper.size();
+        if self.margin.is_nan() || self.margin < 0. {
+            return Err(format!("margin {} is negative", self.margin));
+        }
+        if 2. * self.margin >= width.min(height) {
+            return Err(format!(
+                "margin {} leaves nothing of a {} by {} page to draw on",
+                self.margin, width, height
+            ));
+        }
+
+        Ok((
+            (self.margin, self.margin),
+            (width - self.margin, height - self.margin),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn margins_must_leave_something() {
+        let page = |margin| PageOptions {
+            paper: Paper::A4,
+            margin,
+        };
+        let (width, height) = Paper::A4.size();
+        assert_eq!(
+            page(100.).area(),
+            Ok(((100., 100.), (width - 100., height - 100.)))
+        );
+        assert!(page(height / 2.).area().is_err());
+        assert!(page(-1.).area().is_err());
+        assert!(page(f64::NAN).area().is_err());
+    }
+}
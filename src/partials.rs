@@ -0,0 +1,286 @@
+//! Fourier series and their partial sums, for stacking up a waveform one harmonic at a time.
+//!
+//! A series over one period of `2π` is `constant + Σ cosines[k - 1]·cos(kt) + sines[k - 1]·sin(kt)`
+//! for harmonics `k` from 1.
+
+use crate::delaunay::Polyline;
+use crate::sample::Area;
+use num_complex::Complex32;
+use serde::Serialize;
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Series {
+    pub constant: f64,
+    pub cosines: Vec<f64>,
+    pub sines: Vec<f64>,
+}
+
+impl Series {
+    pub fn new(constant: f64, cosines: Vec<f64>, sines: Vec<f64>) -> Self {
+        Self {
+            constant,
+            cosines,
+            sines,
+        }
+    }
+
+    /// A sine series from a formula for each harmonic's coefficient, up to `harmonics`.
+    fn sines<F: Fn(f64) -> f64>(harmonics: usize, coefficient: F) -> Self {
+        let sines = (1..=harmonics).map(|k| coefficient(k as f64)).collect();
+        Self::new(0., Vec::new(), sines)
+    }
+
+    /// A square wave between -1 and 1: odd harmonics, falling off as `1/k`.
+    pub fn square(harmonics: usize) -> Self {
+        Self::sines(harmonics, |k| match k as usize & 1 {
+            1 => 4. / (PI * k),
+            _ => 0.,
+        })
+    }
+
+    /// A sawtooth rising from -1 to 1: every harmonic, alternating in sign, falling off as `1/k`.
+    pub fn sawtooth(harmonics: usize) -> Self {
+        Self::sines(harmonics, |k| match k as usize & 1 {
+            1 => 2. / (PI * k),
+            _ => -2. / (PI * k),
+        })
+    }
+
+    /// A triangle wave between -1 and 1: odd harmonics, alternating in sign, falling off as
+    /// `1/k²`.
+    pub fn triangle(harmonics: usize) -> Self {
+        Self::sines(harmonics, |k| match k as usize % 4 {
+            1 => 8. / (PI * PI * k * k),
+            3 => -8. / (PI * PI * k * k),
+            _ => 0.,
+        })
+    }
+
+    /// The series up to `harmonics` for a curve sampled evenly over one period, by FFT. The
+    /// samples should start at `t = 0` and stop just short of `t = 2π`; more than twice as many
+    /// as `harmonics` are needed for the top ones to mean anything.
+    pub fn from_samples(samples: &[f64], harmonics: usize) -> Self {
+        assert!(!samples.is_empty(), "no samples to take a series from");
+        let n = samples.len();
+        let mut buffer = samples
+            .iter()
+            .map(|&s| Complex32::new(s as f32, 0.))
+            .collect::<Vec<_>>();
+        fourier::create_fft_f32(n).fft_in_place(&mut buffer);
+
+        // rounding leaves specks in harmonics that should be empty; clear them out so they
+        // don't each get a partial sum of their own
+        let largest = buffer.iter().fold(0f32, |max, c| max.max(c.norm()));
+        let clean = |x: f32| {
+            if x.abs() > largest * 1e-5 {
+                x as f64
+            } else {
+                0.
+            }
+        };
+
+        let scale = 2. / n as f64;
+        let bins = &buffer[1..=harmonics.min(n.saturating_sub(1) / 2)];
+        Self::new(
+            buffer.first().map_or(0., |c| clean(c.re) / n as f64),
+            bins.iter().map(|c| clean(c.re) * scale).collect(),
+            bins.iter().map(|c| -clean(c.im) * scale).collect(),
+        )
+    }
+
+    /// The harmonics with something to add, in order.
+    pub fn terms(&self) -> Vec<usize> {
+        let len = self.cosines.len().max(self.sines.len());
+        (1..=len)
+            .filter(|&k| {
+                let a = self.cosines.get(k - 1).copied().unwrap_or(0.);
+                let b = self.sines.get(k - 1).copied().unwrap_or(0.);
+                a != 0. || b != 0.
+            })
+            .collect()
+    }
+
+    /// Harmonic `k` alone, at `t`.
+    fn term(&self, k: usize, t: f64) -> f64 {
+        let a = self.cosines.get(k - 1).copied().unwrap_or(0.);
+        let b = self.sines.get(k - 1).copied().unwrap_or(0.);
+        let kt = k as f64 * t;
+        a * kt.cos() + b * kt.sin()
+    }
+
+    /// The whole series at `t`.
+    pub fn value(&self, t: f64) -> f64 {
+        self.terms()
+            .into_iter()
+            .fold(self.constant, |sum, k| sum + self.term(k, t))
+    }
+
+    /// The first `count` partial sums over `ts`, each adding the next harmonic with something to
+    /// add; fewer if the series runs out first.
+    pub fn partials(&self, count: usize, ts: &[f64]) -> Vec<Vec<f64>> {
+        let mut running = vec![self.constant; ts.len()];
+        self.terms()
+            .into_iter()
+            .take(count)
+            .map(|k| {
+                for (sum, &t) in running.iter_mut().zip(ts) {
+                    *sum += self.term(k, t);
+                }
+                running.clone()
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Wave {
+    Square,
+    Sawtooth,
+    Triangle,
+}
+
+impl FromStr for Wave {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Wave::Square),
+            "sawtooth" | "saw" => Ok(Wave::Sawtooth),
+            "triangle" => Ok(Wave::Triangle),
+            _ => Err(format!(
+                "unknown wave {:?} (expected square, sawtooth or triangle)",
+                s
+            )),
+        }
+    }
+}
+
+impl Wave {
+    pub fn series(self, harmonics: usize) -> Series {
+        match self {
+            Wave::Square => Series::square(harmonics),
+            Wave::Sawtooth => Series::sawtooth(harmonics),
+            Wave::Triangle => Series::triangle(harmonics),
+        }
+    }
+}
+
+/// `grains` evenly spaced times across `periods` periods, both ends included.
+pub fn times(periods: f64, grains: usize) -> Vec<f64> {
+    let end = periods * 2. * PI;
+    (0..grains)
+        .map(|i| end * i as f64 / (grains.max(2) - 1) as f64)
+        .collect()
+}
+
+/// `rows` scaled by `scale` and each raised `offset` above the one before.
+pub fn stack(rows: &[Vec<f64>], offset: f64, scale: f64) -> Vec<Vec<f64>> {
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| row.iter().map(|v| v * scale + i as f64 * offset).collect())
+        .collect()
+}
+
+/// `rows` of values over `ts` as lines stretched to fill `area`: times across, values up.
+pub fn layout(ts: &[f64], rows: &[Vec<f64>], area: Area) -> Vec<Polyline> {
+    let ((x0, y0), (x1, y1)) = area;
+    let extent = |values: &mut dyn Iterator<Item = f64>| {
+        values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        })
+    };
+    let (t_min, t_max) = extent(&mut ts.iter().copied());
+    let (v_min, v_max) = extent(&mut rows.iter().flatten().copied());
+
+    // flat extents land in the middle rather than dividing by zero
+    let map = |v: f64, min: f64, max: f64, to: (f64, f64)| match max - min {
+        d if d > 0. => to.0 + (to.1 - to.0) * (v - min) / d,
+        _ => (to.0 + to.1) / 2.,
+    };
+    rows.iter()
+        .map(|row| {
+            ts.iter()
+                .zip(row)
+                .map(|(&t, &v)| {
+                    (
+                        map(t, t_min, t_max, (x0, x1)),
+                        map(v, v_min, v_max, (y0, y1)),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn waves_converge() {
+        let ts = [PI / 4., PI / 2., 3. * PI / 4., 3. * PI / 2.];
+        let expected = [
+            (Wave::Square, [1., 1., 1., -1.]),
+            (Wave::Sawtooth, [0.25, 0.5, 0.75, -0.5]),
+            (Wave::Triangle, [0.5, 1., 0.5, -1.]),
+        ];
+        for &(wave, values) in &expected {
+            let series = wave.series(2000);
+            for (&t, &value) in ts.iter().zip(&values) {
+                assert!((series.value(t) - value).abs() < 0.01, "{:?} {}", wave, t);
+            }
+        }
+    }
+
+    #[test]
+    fn partials_add_a_term_at_a_time() {
+        let series = Series::square(7);
+        assert_eq!(series.terms(), &[1, 3, 5, 7]);
+
+        let ts = times(1., 9);
+        let partials = series.partials(10, &ts);
+        assert_eq!(partials.len(), 4);
+        assert!(partials.iter().all(|partial| partial.len() == 9));
+        // the first is the fundamental alone
+        let peak = 4. / PI;
+        assert!((partials[0][2] - peak).abs() < 1e-9);
+        assert!((partials[1][2] - (peak - peak / 3.)).abs() < 1e-9);
+        assert_eq!(series.partials(2, &ts).len(), 2);
+    }
+
+    #[test]
+    fn coefficients_from_samples() {
+        let samples = (0..64)
+            .map(|i| {
+                let t = 2. * PI * i as f64 / 64.;
+                0.5 + 2. * t.sin() - (3. * t).cos() + 0.25 * (5. * t).sin()
+            })
+            .collect::<Vec<_>>();
+        let series = Series::from_samples(&samples, 8);
+        assert_eq!(series.terms(), &[1, 3, 5]);
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-4;
+        assert!(close(series.constant, 0.5));
+        assert!(close(series.sines[0], 2.));
+        assert!(close(series.cosines[2], -1.));
+        assert!(close(series.sines[4], 0.25));
+        assert!(series
+            .sines
+            .iter()
+            .chain(&series.cosines)
+            .all(|&c| c == 0. || c.abs() > 0.2));
+    }
+
+    #[test]
+    fn stacks_fill_the_area() {
+        let ts = [0., 1., 2.];
+        let rows = stack(&[vec![0., 1., 0.], vec![0., -1., 0.]], 3., 2.);
+        assert_eq!(rows, &[vec![0., 2., 0.], vec![3., 1., 3.]]);
+
+        let lines = layout(&ts, &rows, ((0., 0.), (20., 30.)));
+        assert_eq!(lines[0], &[(0., 0.), (10., 20.), (20., 0.)]);
+        assert_eq!(lines[1], &[(0., 30.), (10., 10.), (20., 30.)]);
+    }
+}
@@ -0,0 +1,32 @@
+use hpgl::sender::Progress;
+use hpgl::Coordinate;
+use std::io::{self, Write};
+
+const BAR_WIDTH: usize = 40;
+
+/// Draws a progress bar for a plot in flight on stderr, redrawing it in place. Meant to be handed
+/// straight to [`hpgl::sender::Sender::on_progress`].
+pub fn bar(progress: &Progress) {
+    let fraction = progress.fraction();
+    let filled = (fraction * BAR_WIDTH as f32).round() as usize;
+    let eta = progress.remaining.as_secs();
+
+    let mut stderr = io::stderr();
+    let _ = write!(
+        stderr,
+        "\r[{}{}] {:5.1}% {}/{} cmds, {:.1} kB, {:.2} m drawn, ETA {}:{:02}",
+        "#".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        fraction * 100.,
+        progress.commands_sent,
+        progress.commands_total,
+        progress.bytes_sent as f32 / 1000.,
+        progress.pen_down_distance / Coordinate::UNITS_PER_CM / 100.,
+        eta / 60,
+        eta % 60,
+    );
+    if progress.commands_sent == progress.commands_total {
+        let _ = writeln!(stderr);
+    }
+    let _ = stderr.flush();
+}
@@ -0,0 +1,252 @@
+//! Grayscale images, for driving generators by tone. Reads PGM, and PNG of any colour type,
+//! which is converted to gray.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A grayscale image with brightness in `0.0` (black) to `1.0` (white), row by row from the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Raster {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<f64>,
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Rec. 601 luma, as most grayscale conversions use.
+fn luma(r: f64, g: f64, b: f64) -> f64 {
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+/// Splits the start of a PGM file into whitespace-separated header fields, skipping `#` comments.
+/// Returns the fields and the offset just past the single whitespace byte ending the last one.
+fn header_fields(data: &[u8], count: usize) -> io::Result<(Vec<&str>, usize)> {
+    let mut fields = Vec::with_capacity(count);
+    let mut i = 0;
+    while fields.len() < count {
+        match data.get(i) {
+            None => return Err(invalid("truncated PGM header")),
+            Some(b'#') => {
+                while !matches!(data.get(i), None | Some(b'\n')) {
+                    i += 1;
+                }
+            }
+            Some(c) if c.is_ascii_whitespace() => i += 1,
+            Some(_) => {
+                let start = i;
+                while matches!(data.get(i), Some(c) if !c.is_ascii_whitespace()) {
+                    i += 1;
+                }
+                let field = std::str::from_utf8(&data[start..i])
+                    .map_err(|_| invalid("malformed PGM header"))?;
+                fields.push(field);
+            }
+        }
+    }
+
+    Ok((fields, i + 1))
+}
+
+impl Raster {
+    pub fn new(width: usize, height: usize, pixels: Vec<f64>) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Loads a PNG or PGM image from a file, telling them apart by their contents.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        if data.starts_with(PNG_SIGNATURE) {
+            Self::from_png(&data)
+        } else {
+            Self::from_pgm(&data)
+        }
+    }
+
+    /// Decodes a PNG image. Colour is reduced to luma, and transparency is composited onto white
+    /// paper.
+    pub fn from_png(data: &[u8]) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(data);
+        // palettes and low bit depths out to 8 bit samples, so there's only four layouts left
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buffer)?;
+
+        let (width, height) = (info.width as usize, info.height as usize);
+        if width == 0 || height == 0 {
+            return Err(invalid("empty PNG image"));
+        }
+        let (color_type, _) = reader.output_color_type();
+        let channels = color_type.samples();
+        let line_size = buffer.len() / height.max(1);
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for line in buffer.chunks(line_size.max(1)).take(height) {
+            for pixel in line.chunks_exact(channels).take(width) {
+                let sample = |i: usize| pixel[i] as f64 / 255.;
+                let (gray, alpha) = match color_type {
+                    png::ColorType::Grayscale => (sample(0), 1.),
+                    png::ColorType::GrayscaleAlpha => (sample(0), sample(1)),
+                    png::ColorType::RGB => (luma(sample(0), sample(1), sample(2)), 1.),
+                    png::ColorType::RGBA => (luma(sample(0), sample(1), sample(2)), sample(3)),
+                    png::ColorType::Indexed => return Err(invalid("unexpanded PNG palette")),
+                };
+                pixels.push(gray * alpha + (1. - alpha));
+            }
+        }
+        if pixels.len() != width * height {
+            return Err(invalid("truncated PNG data"));
+        }
+
+        Ok(Self::new(width, height, pixels))
+    }
+
+    /// Parses a binary (`P5`) or plain (`P2`) PGM image, of any bit depth.
+    pub fn from_pgm(data: &[u8]) -> io::Result<Self> {
+        let (fields, offset) = header_fields(data, 4)?;
+        let number = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| invalid("malformed PGM header"))
+        };
+        let (width, height, max) = (number(fields[1])?, number(fields[2])?, number(fields[3])?);
+        if max == 0 || max > 65535 {
+            return Err(invalid("PGM maximum value out of range"));
+        }
+        if width == 0 || height == 0 {
+            return Err(invalid("empty PGM image"));
+        }
+        let size = width
+            .checked_mul(height)
+            .ok_or_else(|| invalid("PGM image too large"))?;
+
+        let samples: Vec<usize> = match fields[0] {
+            "P5" if max < 256 => data[offset.min(data.len())..]
+                .iter()
+                .map(|&b| b as usize)
+                .collect(),
+            "P5" => data[offset.min(data.len())..]
+                .chunks_exact(2)
+                .map(|pair| (pair[0] as usize) << 8 | pair[1] as usize)
+                .collect(),
+            "P2" => std::str::from_utf8(&data[offset.min(data.len())..])
+                .map_err(|_| invalid("malformed PGM data"))?
+                .split_ascii_whitespace()
+                .map(number)
+                .collect::<io::Result<_>>()?,
+            _ => return Err(invalid("not a grayscale PGM (expected P2 or P5)")),
+        };
+        if samples.len() < size {
+            return Err(invalid("truncated PGM data"));
+        }
+
+        let pixels = samples
+            .into_iter()
+            .take(size)
+            .map(|s| s.min(max) as f64 / max as f64)
+            .collect();
+        Ok(Self::new(width, height, pixels))
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f64 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Brightness at `(u, v)` in the unit square, `v` running up from the bottom of the image as
+    /// plotter coordinates do; nearest pixel, clamped to the edges.
+    pub fn sample(&self, (u, v): (f64, f64)) -> f64 {
+        let cell = |t: f64, n: usize| ((t * n as f64) as i64).max(0).min(n as i64 - 1) as usize;
+        self.get(cell(u, self.width), self.height - 1 - cell(v, self.height))
+    }
+
+    /// The largest rectangle with the image's aspect ratio centred in `min`..`max`.
+    pub fn fit(&self, min: (f64, f64), max: (f64, f64)) -> ((f64, f64), (f64, f64)) {
+        let (w, h) = (max.0 - min.0, max.1 - min.1);
+        let scale = (w / self.width as f64).min(h / self.height as f64);
+        let (fw, fh) = (self.width as f64 * scale, self.height as f64 * scale);
+        let (x, y) = (min.0 + (w - fw) / 2., min.1 + (h - fh) / 2.);
+        ((x, y), (x + fw, y + fh))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pgm() {
+        let plain = b"P2\n# a comment\n3 2\n4\n0 1 2\n3 4 9\n";
+        let raster = Raster::from_pgm(plain).unwrap();
+        assert_eq!((raster.width, raster.height), (3, 2));
+        assert_eq!(raster.pixels, &[0., 0.25, 0.5, 0.75, 1., 1.]);
+
+        let mut binary = b"P5 2 1 255\n".to_vec();
+        binary.extend(&[0, 255]);
+        assert_eq!(Raster::from_pgm(&binary).unwrap().pixels, &[0., 1.]);
+
+        let mut wide = b"P5 1 1 65535\n".to_vec();
+        wide.extend(&[0x80, 0x00]);
+        assert!((Raster::from_pgm(&wide).unwrap().pixels[0] - 0.5).abs() < 1e-4);
+
+        assert!(Raster::from_pgm(b"P5 2 2 255\n\x00").is_err());
+        assert!(Raster::from_pgm(b"P6 1 1 255\n\x00\x00\x00").is_err());
+    }
+
+    #[test]
+    fn pgm_dimensions() {
+        assert!(Raster::from_pgm(b"P5 0 0 255\n").is_err());
+        assert!(Raster::from_pgm(b"P2 3 0 255\n").is_err());
+
+        // the product overflows, rather than asking for a vast amount of data
+        let huge = format!("P5 {} {} 255\n\x00", usize::MAX / 2 + 1, 2);
+        assert!(Raster::from_pgm(huge.as_bytes()).is_err());
+    }
+
+    fn encode(width: u32, height: u32, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut buffer, width, height);
+            encoder.set_color(color);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(data).unwrap();
+        }
+        buffer
+    }
+
+    #[test]
+    fn png() {
+        let gray = encode(2, 2, png::ColorType::Grayscale, &[0, 51, 204, 255]);
+        assert!(gray.starts_with(PNG_SIGNATURE));
+        let raster = Raster::from_png(&gray).unwrap();
+        assert_eq!((raster.width, raster.height), (2, 2));
+        assert_eq!(raster.pixels, &[0., 0.2, 0.8, 1.]);
+
+        // pure red, and black at half opacity
+        let rgba = encode(2, 1, png::ColorType::RGBA, &[255, 0, 0, 255, 0, 0, 0, 128]);
+        let raster = Raster::from_png(&rgba).unwrap();
+        assert!((raster.pixels[0] - 0.299).abs() < 1e-9);
+        assert!((raster.pixels[1] - (1. - 128. / 255.)).abs() < 1e-9);
+
+        assert!(Raster::from_png(&gray[..gray.len() - 20]).is_err());
+    }
+
+    #[test]
+    fn sampling_is_bottom_up() {
+        let raster = Raster::new(2, 2, vec![0., 0.25, 0.5, 0.75]);
+        assert_eq!(raster.sample((0.1, 0.9)), 0.);
+        assert_eq!(raster.sample((0.9, 0.1)), 0.75);
+        assert_eq!(raster.sample((-1., 2.)), 0.);
+        assert_eq!(raster.fit((0., 0.), (4., 2.)), ((1., 0.), (3., 2.)));
+    }
+}
@@ -0,0 +1,169 @@
+//! Stacks of lines that hide one another, as on the cover of Unknown Pleasures: each line is
+//! drawn only where it rises above every line in front of it.
+
+use crate::delaunay::Polyline;
+use crate::sample::Area;
+use std::f64::consts::PI;
+use std::mem;
+
+/// The visible pieces of each row, working from the front: a row shows only where it's above
+/// the highest of the rows before it (a "floating horizon"). Rows are values at evenly spaced
+/// positions, all the same length; the pieces come out as `(position, value)` with positions
+/// counting samples, cut where a row dips behind the horizon.
+pub fn hidden(rows: &[Vec<f64>]) -> Vec<Vec<Polyline>> {
+    let len = rows.first().map_or(0, Vec::len);
+    let mut horizon = vec![f64::NEG_INFINITY; len];
+
+    rows.iter()
+        .map(|row| {
+            assert_eq!(row.len(), len, "rows must all be the same length");
+
+            let mut pieces = Vec::new();
+            let mut piece = Vec::new();
+            let visible = |i: usize| row[i] > horizon[i];
+            if len > 0 && visible(0) {
+                piece.push((0., row[0]));
+            }
+            for i in 1..len {
+                if visible(i - 1) != visible(i) {
+                    // where the row crosses the horizon
+                    let (before, after) = (row[i - 1] - horizon[i - 1], row[i] - horizon[i]);
+                    let t = before / (before - after);
+                    piece.push(((i - 1) as f64 + t, row[i - 1] + t * (row[i] - row[i - 1])));
+                    if !visible(i) {
+                        pieces.push(mem::take(&mut piece));
+                    }
+                }
+                if visible(i) {
+                    piece.push((i as f64, row[i]));
+                }
+            }
+            pieces.push(piece);
+            pieces.retain(|piece| piece.len() > 1);
+
+            for (h, &value) in horizon.iter_mut().zip(row) {
+                *h = h.max(value);
+            }
+            pieces
+        })
+        .collect()
+}
+
+/// Ridge lines filling `area`, one per row of `values` (each in `0.0..=1.0`), the first row at
+/// the back. Each ridge rises up to `lift` times the gap between ridges.
+pub fn ridges(values: &[Vec<f64>], area: Area, lift: f64) -> Vec<Polyline> {
+    let ((x0, y0), (x1, y1)) = area;
+    let count = values.len();
+    let gap = (y1 - y0) / ((count.max(1) - 1) as f64 + lift);
+    let len = values.first().map_or(0, Vec::len);
+
+    // front to back
+    let rows = values
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(r, row)| {
+            let base = y0 + r as f64 * gap;
+            row.iter().map(|v| base + v * lift * gap).collect()
+        })
+        .collect::<Vec<_>>();
+
+    let x = |position: f64| x0 + (x1 - x0) * position / (len.max(2) - 1) as f64;
+    hidden(&rows)
+        .into_iter()
+        .flatten()
+        .map(|piece| piece.into_iter().map(|(p, y)| (x(p), y)).collect())
+        .collect()
+}
+
+/// Concentric rings between radii `inner` and `outer` around `centre`, one per row of `values`
+/// (each in `0.0..=1.0`, running once round), the first row innermost and in front. Each ring
+/// swells outwards by up to `lift` times the gap between rings.
+pub fn rings(
+    values: &[Vec<f64>],
+    centre: (f64, f64),
+    inner: f64,
+    outer: f64,
+    lift: f64,
+) -> Vec<Polyline> {
+    let count = values.len();
+    let gap = (outer - inner) / ((count.max(1) - 1) as f64 + lift);
+    let len = values.first().map_or(0, Vec::len);
+
+    // closed, so back to the start at the end
+    let rows = values
+        .iter()
+        .enumerate()
+        .map(|(r, row)| {
+            let base = inner + r as f64 * gap;
+            row.iter()
+                .chain(row.first())
+                .map(|v| base + v * lift * gap)
+                .collect()
+        })
+        .collect::<Vec<_>>();
+
+    let point = |position: f64, radius: f64| {
+        let theta = 2. * PI * position / len.max(1) as f64;
+        (
+            centre.0 + radius * theta.cos(),
+            centre.1 + radius * theta.sin(),
+        )
+    };
+    hidden(&rows)
+        .into_iter()
+        .flatten()
+        .map(|piece| piece.into_iter().map(|(p, r)| point(p, r)).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hides_behind_the_horizon() {
+        let rows = vec![
+            vec![0., 2., 0., 0.],
+            // tucked behind the first row's peak, then clear of it
+            vec![1., 1., 1., 1.],
+            // entirely behind
+            vec![0.5, 0.5, 0.5, 0.5],
+        ];
+        let pieces = hidden(&rows);
+
+        assert_eq!(pieces[0], &[vec![(0., 0.), (1., 2.), (2., 0.), (3., 0.)]]);
+        assert_eq!(
+            pieces[1],
+            &[
+                vec![(0., 1.), (0.5, 1.)],
+                vec![(1.5, 1.), (2., 1.), (3., 1.)]
+            ]
+        );
+        assert!(pieces[2].is_empty());
+    }
+
+    #[test]
+    fn ridges_fill_the_area() {
+        let values = vec![vec![0., 1., 0.], vec![0., 0., 0.]];
+        let lines = ridges(&values, ((0., 0.), (10., 30.)), 2.);
+        // the front ridge is flat along the bottom; the back one peaks at the top
+        assert_eq!(lines[0], &[(0., 0.), (5., 0.), (10., 0.)]);
+        assert_eq!(lines[1], &[(0., 10.), (5., 30.), (10., 10.)]);
+    }
+
+    #[test]
+    fn rings_close_up() {
+        let values = vec![vec![0.; 8], vec![0.; 8]];
+        let lines = rings(&values, (0., 0.), 1., 2., 1.);
+        assert_eq!(lines.len(), 2);
+        for (line, radius) in lines.iter().zip(&[1., 1.5]) {
+            assert_eq!(line.len(), 9);
+            let (first, last) = (line[0], line[8]);
+            assert!((first.0 - last.0).abs() < 1e-9 && (first.1 - last.1).abs() < 1e-9);
+            assert!(line
+                .iter()
+                .all(|&(x, y)| (x.hypot(y) - radius).abs() < 1e-9));
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! Ways of scattering points over the page, for generators to triangulate, stipple or connect.
+//!
+//! Every sampler fills an area given as `(min, max)` corners, as returned by
+//! [`PageOptions::area`](crate::page::PageOptions::area).
+
+use crate::raster::Raster;
+use rand::Rng;
+use serde::Serialize;
+use std::f64::consts::PI;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+pub type Area = ((f64, f64), (f64, f64));
+
+fn size(((x0, y0), (x1, y1)): Area) -> (f64, f64) {
+    (x1 - x0, y1 - y0)
+}
+
+/// Maps `(u, v)` in the unit square onto `area`.
+fn place(((x0, y0), (x1, y1)): Area, (u, v): (f64, f64)) -> (f64, f64) {
+    (x0 + u * (x1 - x0), y0 + v * (y1 - y0))
+}
+
+/// Independent uniformly random points. Clumps and gaps are to be expected.
+pub fn uniform<R: Rng>(rng: &mut R, n: usize, area: Area) -> Vec<(f64, f64)> {
+    (0..n)
+        .map(|_| place(area, (rng.gen(), rng.gen())))
+        .collect()
+}
+
+/// Random points no closer than `radius` to each other, packed until there's no room for more,
+/// by Bridson's algorithm.
+pub fn poisson_disc<R: Rng>(rng: &mut R, radius: f64, area: Area) -> Vec<(f64, f64)> {
+    // candidates tried around each point before giving up on it
+    const ATTEMPTS: usize = 30;
+
+    let ((x0, y0), _) = area;
+    let (width, height) = size(area);
+    let cell = radius / 2f64.sqrt();
+    let (cols, rows) = (
+        (width / cell).ceil().max(1.) as usize,
+        (height / cell).ceil().max(1.) as usize,
+    );
+    let grid_index = |(x, y): (f64, f64)| {
+        let col = (((x - x0) / cell) as usize).min(cols - 1);
+        let row = (((y - y0) / cell) as usize).min(rows - 1);
+        (col, row)
+    };
+
+    // each cell is small enough to hold at most one point
+    let mut grid: Vec<Option<usize>> = vec![None; cols * rows];
+    let mut points = Vec::new();
+    let mut active = Vec::new();
+
+    let first = place(area, (rng.gen(), rng.gen()));
+    let (col, row) = grid_index(first);
+    grid[row * cols + col] = Some(0);
+    points.push(first);
+    active.push(0);
+
+    while !active.is_empty() {
+        let slot = rng.gen_range(0, active.len());
+        let centre = points[active[slot]];
+
+        let found = (0..ATTEMPTS).find_map(|_| {
+            let theta = rng.gen_range(0., 2. * PI);
+            let r = rng.gen_range(radius, 2. * radius);
+            let candidate = (centre.0 + r * theta.cos(), centre.1 + r * theta.sin());
+            if candidate.0 < x0
+                || candidate.1 < y0
+                || candidate.0 > x0 + width
+                || candidate.1 > y0 + height
+            {
+                return None;
+            }
+
+            let (col, row) = grid_index(candidate);
+            let crowded = (row.saturating_sub(2)..(row + 3).min(rows)).any(|r| {
+                (col.saturating_sub(2)..(col + 3).min(cols)).any(|c| match grid[r * cols + c] {
+                    Some(i) => {
+                        let p = points[i];
+                        (p.0 - candidate.0).hypot(p.1 - candidate.1) < radius
+                    }
+                    None => false,
+                })
+            });
+            if crowded {
+                None
+            } else {
+                Some((candidate, row * cols + col))
+            }
+        });
+
+        match found {
+            Some((candidate, cell)) => {
+                grid[cell] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+            }
+            None => {
+                active.swap_remove(slot);
+            }
+        }
+    }
+
+    points
+}
+
+/// The Poisson-disc radius that fills `area` with roughly `n` points.
+pub fn poisson_radius(n: usize, area: Area) -> f64 {
+    // Bridson's algorithm settles at around 0.7 points per radius squared
+    let (width, height) = size(area);
+    (0.7 * width * height / n.max(1) as f64).sqrt()
+}
+
+/// One random point in each cell of a grid of about `n` roughly square cells.
+pub fn jittered_grid<R: Rng>(rng: &mut R, n: usize, area: Area) -> Vec<(f64, f64)> {
+    let (width, height) = size(area);
+    let cols = ((n as f64 * width / height).sqrt().round() as usize).max(1);
+    let rows = ((n as f64 / cols as f64).round() as usize).max(1);
+
+    (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (col, row)))
+        .map(|(col, row)| {
+            let u = (col as f64 + rng.gen::<f64>()) / cols as f64;
+            let v = (row as f64 + rng.gen::<f64>()) / rows as f64;
+            place(area, (u, v))
+        })
+        .collect()
+}
+
+/// `i` written in `base` and mirrored about the radix point.
+fn radical_inverse(mut i: u64, base: u64) -> f64 {
+    let mut inverse = 0.;
+    let mut scale = 1. / base as f64;
+    while i > 0 {
+        inverse += (i % base) as f64 * scale;
+        i /= base;
+        scale /= base as f64;
+    }
+    inverse
+}
+
+/// The first `n` points of the Halton sequence in bases 2 and 3: evenly spread without looking
+/// gridded. Deterministic; the seed plays no part.
+pub fn halton(n: usize, area: Area) -> Vec<(f64, f64)> {
+    // skip the first point, which sits in the corner
+    (1..=n as u64)
+        .map(|i| place(area, (radical_inverse(i, 2), radical_inverse(i, 3))))
+        .collect()
+}
+
+/// The first `n` points of the two-dimensional Sobol sequence, even more evenly spread than
+/// Halton's at powers of two. Deterministic; the seed plays no part.
+pub fn sobol(n: usize, area: Area) -> Vec<(f64, f64)> {
+    // direction numbers: van der corput in x, and the primitive polynomial x + 1 in y
+    let mut directions = [[0u32; 32]; 2];
+    for bit in 0..32 {
+        directions[0][bit] = 1 << (31 - bit);
+        directions[1][bit] = match bit {
+            0 => 1 << 31,
+            _ => directions[1][bit - 1] ^ (directions[1][bit - 1] >> 1),
+        };
+    }
+
+    let scale = 1. / 2f64.powi(32);
+    let (mut x, mut y) = (0u32, 0u32);
+    (0..n as u64)
+        .map(|i| {
+            // gray code order: flip the direction for the lowest zero bit of the index, which
+            // also skips the first point, in the corner
+            let bit = (!i).trailing_zeros() as usize;
+            x ^= directions[0][bit];
+            y ^= directions[1][bit];
+            place(area, (x as f64 * scale, y as f64 * scale))
+        })
+        .collect()
+}
+
+/// Random points clustered where `raster` is dark, the image fitted into `area` with its aspect
+/// ratio kept. Pure white gets no points at all.
+pub fn weighted<R: Rng>(rng: &mut R, n: usize, raster: &Raster, area: Area) -> Vec<(f64, f64)> {
+    let fitted = raster.fit(area.0, area.1);
+
+    let mut total = 0.;
+    let cumulative = raster
+        .pixels
+        .iter()
+        .map(|brightness| {
+            total += 1. - brightness;
+            total
+        })
+        .collect::<Vec<_>>();
+    if total <= 0. {
+        return Vec::new();
+    }
+
+    (0..n)
+        .map(|_| {
+            let target = rng.gen_range(0., total);
+            let pixel = match cumulative.binary_search_by(|c| c.partial_cmp(&target).unwrap()) {
+                Ok(i) => i + 1,
+                Err(i) => i,
+            }
+            .min(cumulative.len() - 1);
+
+            // rasters run top down, the page bottom up
+            let (col, row) = (pixel % raster.width, pixel / raster.width);
+            let u = (col as f64 + rng.gen::<f64>()) / raster.width as f64;
+            let v = 1. - (row as f64 + rng.gen::<f64>()) / raster.height as f64;
+            place(fitted, (u, v))
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sampler {
+    Uniform,
+    Poisson,
+    Jittered,
+    Halton,
+    Sobol,
+    /// Weighted by the darkness of an image.
+    Image,
+}
+
+impl FromStr for Sampler {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Sampler::Uniform),
+            "poisson" => Ok(Sampler::Poisson),
+            "jittered" => Ok(Sampler::Jittered),
+            "halton" => Ok(Sampler::Halton),
+            "sobol" => Ok(Sampler::Sobol),
+            "image" => Ok(Sampler::Image),
+            _ => Err(format!(
+                "unknown sampler {:?} (expected uniform, poisson, jittered, halton, sobol or image)",
+                s
+            )),
+        }
+    }
+}
+
+/// How a generator scatters its points.
+#[derive(Clone, Debug, Serialize, StructOpt)]
+pub struct SampleOptions {
+    /// How to place points: uniform, poisson (evenly spaced at random), jittered (grid),
+    /// halton, sobol or image (denser where --image is darker)
+    #[structopt(long, default_value = "uniform")]
+    pub sampler: Sampler,
+    /// Grayscale PNG or PGM image for the image sampler
+    #[structopt(long, parse(from_os_str), required_if("sampler", "image"))]
+    pub image: Option<PathBuf>,
+}
+
+impl SampleOptions {
+    /// About `n` points over `area`, drawn with `rng`. Only the image sampler can fail, on
+    /// reading its image.
+    pub fn points<R: Rng>(&self, rng: &mut R, n: usize, area: Area) -> io::Result<Vec<(f64, f64)>> {
+        Ok(match self.sampler {
+            Sampler::Uniform => uniform(rng, n, area),
+            Sampler::Poisson => poisson_disc(rng, poisson_radius(n, area), area),
+            Sampler::Jittered => jittered_grid(rng, n, area),
+            Sampler::Halton => halton(n, area),
+            Sampler::Sobol => sobol(n, area),
+            Sampler::Image => {
+                let path = self.image.as_ref().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "the image sampler needs --image",
+                    )
+                })?;
+                weighted(rng, n, &Raster::open(path)?, area)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::seed;
+
+    const AREA: Area = ((100., 200.), (1100., 700.));
+
+    fn inside(points: &[(f64, f64)]) -> bool {
+        let ((x0, y0), (x1, y1)) = AREA;
+        points
+            .iter()
+            .all(|&(x, y)| x >= x0 && x <= x1 && y >= y0 && y <= y1)
+    }
+
+    #[test]
+    fn poisson_disc_keeps_its_distance() {
+        let radius = poisson_radius(200, AREA);
+        let points = poisson_disc(&mut seed::rng(1), radius, AREA);
+        assert!(inside(&points));
+        assert!(points.len() > 150 && points.len() < 250, "{}", points.len());
+        for (i, a) in points.iter().enumerate() {
+            for b in &points[i + 1..] {
+                assert!((a.0 - b.0).hypot(a.1 - b.1) >= radius);
+            }
+        }
+    }
+
+    #[test]
+    fn grids_fill_the_area() {
+        let points = jittered_grid(&mut seed::rng(1), 200, AREA);
+        assert_eq!(points.len(), 200);
+        assert!(inside(&points));
+
+        for points in &[halton(256, AREA), sobol(256, AREA)] {
+            assert_eq!(points.len(), 256);
+            assert!(inside(points));
+            // low discrepancy: each quarter of the area gets its share
+            let left_bottom = points
+                .iter()
+                .filter(|&&(x, y)| x < 600. && y < 450.)
+                .count();
+            assert!((60..=68).contains(&left_bottom), "{}", left_bottom);
+        }
+
+        assert_eq!(
+            sobol(3, ((0., 0.), (1., 1.))),
+            &[(0.5, 0.5), (0.75, 0.25), (0.25, 0.75)]
+        );
+    }
+
+    #[test]
+    fn weighted_avoids_white() {
+        // black on the left, white on the right
+        let raster = Raster::new(2, 1, vec![0., 1.]);
+        let points = weighted(&mut seed::rng(1), 100, &raster, AREA);
+        assert_eq!(points.len(), 100);
+        assert!(points.iter().all(|&(x, _)| (100. ..=600.).contains(&x)));
+
+        let white = Raster::new(1, 1, vec![1.]);
+        assert!(weighted(&mut seed::rng(1), 100, &white, AREA).is_empty());
+    }
+}
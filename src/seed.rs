@@ -0,0 +1,27 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use structopt::StructOpt;
+
+/// RNG handed to generators. A named algorithm rather than `StdRng`, whose output may change
+/// between `rand` releases, so saved seeds keep reproducing the same plot.
+pub type SeededRng = ChaCha8Rng;
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct SeedOptions {
+    /// Seed for the random number generator; a fresh one is picked (and printed) if omitted
+    #[structopt(long)]
+    pub seed: Option<u64>,
+}
+
+impl SeedOptions {
+    /// Picks the seed for this run, printing it to stderr so a good result can be recreated.
+    pub fn resolve(&self) -> u64 {
+        let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        eprintln!("seed: {}", seed);
+        seed
+    }
+}
+
+pub fn rng(seed: u64) -> SeededRng {
+    SeededRng::seed_from_u64(seed)
+}
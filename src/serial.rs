@@ -0,0 +1,111 @@
+use hpgl::hp7470a::{DeviceControlInstruction, HandshakeConfig, HandshakeMode, PlotterConfig};
+use hpgl::PlotterWriteable;
+use serialport::{self, DataBits, FlowControl, Parity, SerialPort, SerialPortSettings, StopBits};
+use std::error::Error;
+use std::str::FromStr;
+use std::time::Duration;
+use structopt::StructOpt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Handshake {
+    XonXoff,
+    Hardware,
+    None,
+}
+
+impl FromStr for Handshake {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "xonxoff" => Ok(Handshake::XonXoff),
+            "hardware" => Ok(Handshake::Hardware),
+            "none" => Ok(Handshake::None),
+            _ => Err(format!(
+                "unknown handshake {:?} (expected xonxoff, hardware or none)",
+                s
+            )),
+        }
+    }
+}
+
+/// A baud rate, which has to be above zero.
+pub fn parse_baud(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(0) => Err("baud rate must be above zero".to_string()),
+        Ok(baud) => Ok(baud),
+        Err(e) => Err(format!("invalid baud rate {:?}: {}", s, e)),
+    }
+}
+
+/// Where the plotter is and how to talk to it.
+#[derive(Clone, Debug, StructOpt)]
+pub struct PortOptions {
+    /// Serial port the plotter is attached to
+    #[structopt(long, default_value = "/dev/ttyUSB0")]
+    pub port: String,
+    #[structopt(long, default_value = "9600", parse(try_from_str = parse_baud))]
+    pub baud: u32,
+    /// Flow control: xonxoff, hardware or none
+    #[structopt(long, default_value = "xonxoff")]
+    pub handshake: Handshake,
+}
+
+impl Default for PortOptions {
+    fn default() -> Self {
+        Self {
+            port: "/dev/ttyUSB0".to_string(),
+            baud: 9600,
+            handshake: Handshake::XonXoff,
+        }
+    }
+}
+
+impl PortOptions {
+    /// Opens the port and configures the plotter's end of the handshake to match.
+    pub fn open(&self) -> Result<Box<dyn SerialPort>, Box<dyn Error>> {
+        let mut sp = serialport::open_with_settings(
+            &self.port,
+            &SerialPortSettings {
+                baud_rate: self.baud,
+                data_bits: DataBits::Eight,
+                stop_bits: StopBits::One,
+                parity: Parity::None,
+                flow_control: match self.handshake {
+                    Handshake::XonXoff => FlowControl::Software,
+                    Handshake::Hardware => FlowControl::Hardware,
+                    Handshake::None => FlowControl::None,
+                },
+                timeout: Duration::from_secs(20),
+            },
+        )?;
+
+        match self.handshake {
+            Handshake::XonXoff => {
+                DeviceControlInstruction::SetPlotterConfig(Default::default()).write(&mut sp)?;
+                DeviceControlInstruction::SetHandshakeMode(
+                    HandshakeMode::Mode2,
+                    HandshakeConfig::XonXoff {
+                        xoff_threshold: 80,
+                        xon_trigger_chars: b"\x11".to_vec(),
+                    },
+                )
+                .write(&mut sp)?;
+                DeviceControlInstruction::SetExtHandshakeOptions {
+                    interchar_delay: None,
+                    xoff_trigger_chars: b"\x13".to_vec(),
+                }
+                .write(&mut sp)?;
+            }
+            Handshake::Hardware => {
+                DeviceControlInstruction::SetPlotterConfig(PlotterConfig::HW_HANDSHAKE)
+                    .write(&mut sp)?;
+            }
+            Handshake::None => {
+                DeviceControlInstruction::SetPlotterConfig(Default::default()).write(&mut sp)?;
+            }
+        }
+
+        Ok(sp)
+    }
+}
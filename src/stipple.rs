@@ -0,0 +1,289 @@
+//! Lloyd relaxation: points repeatedly moved to the centroids of their Voronoi cells, until
+//! they're evenly spread. Weighting the centroids by an image's darkness gives Secord's weighted
+//! Voronoi stippling, where the dots settle densely but evenly over the dark areas.
+//!
+//! Cells are measured by sampling a grid over the area rather than clipping polygons, which
+//! keeps the cells on the hull bounded and makes weighting by an image straightforward.
+
+use crate::delaunay::{circle, Diagram, Polyline};
+use crate::raster::Raster;
+use crate::sample::Area;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// A grid of weighted sample points covering the area to relax over.
+pub struct Relaxation {
+    samples: Vec<((f64, f64), f64)>,
+}
+
+/// Finds the nearest of a set of points, walking the Delaunay graph from a starting guess. The
+/// walk never gets stuck short of the nearest point, and is only a step or two long when the
+/// guess is the answer for a nearby query.
+struct Nearest<'a> {
+    points: &'a [(f64, f64)],
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl<'a> Nearest<'a> {
+    fn new(points: &'a [(f64, f64)]) -> Self {
+        let mut neighbours = vec![Vec::new(); points.len()];
+        // too few points, or all in a line: fall back to checking every one
+        match Diagram::new(points.to_vec()) {
+            Some(diagram) => {
+                for (a, b) in diagram.edges() {
+                    neighbours[a].push(b);
+                    neighbours[b].push(a);
+                }
+            }
+            None => {
+                for (i, list) in neighbours.iter_mut().enumerate() {
+                    list.extend((0..points.len()).filter(|&j| j != i));
+                }
+            }
+        }
+
+        Self { points, neighbours }
+    }
+
+    /// A point the walk can start from; duplicates are left out of the triangulation, so not
+    /// every point is connected.
+    fn start(&self) -> usize {
+        (0..self.points.len())
+            .find(|&i| !self.neighbours[i].is_empty())
+            .unwrap_or(0)
+    }
+
+    fn find(&self, from: usize, (x, y): (f64, f64)) -> usize {
+        let distance = |i: usize| {
+            let (px, py) = self.points[i];
+            (px - x) * (px - x) + (py - y) * (py - y)
+        };
+
+        let mut nearest = from;
+        let mut best = distance(from);
+        loop {
+            let closer = self.neighbours[nearest]
+                .iter()
+                .map(|&i| (i, distance(i)))
+                .filter(|&(_, d)| d < best)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            match closer {
+                Some((i, d)) => {
+                    nearest = i;
+                    best = d;
+                }
+                None => return nearest,
+            }
+        }
+    }
+}
+
+impl Relaxation {
+    /// Even weighting over `area`, sampled `resolution` times along its longer side.
+    pub fn uniform(area: Area, resolution: usize) -> Self {
+        let ((x0, y0), (x1, y1)) = area;
+        let step = (x1 - x0).max(y1 - y0) / resolution.max(1) as f64;
+        let (cols, rows) = (
+            ((x1 - x0) / step).round().max(1.) as usize,
+            ((y1 - y0) / step).round().max(1.) as usize,
+        );
+
+        let samples = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (col, row)))
+            .map(|(col, row)| {
+                let x = x0 + (x1 - x0) * (col as f64 + 0.5) / cols as f64;
+                let y = y0 + (y1 - y0) * (row as f64 + 0.5) / rows as f64;
+                ((x, y), 1.)
+            })
+            .collect();
+        Self { samples }
+    }
+
+    /// Weighted by the darkness of each of `raster`'s pixels, fitted into `area` with its
+    /// aspect ratio kept. `gamma` above 1 pushes dots out of the midtones into the shadows; it has
+    /// to be finite and above zero, or white would weigh as much as black or more.
+    pub fn weighted(raster: &Raster, area: Area, gamma: f64) -> Result<Self, String> {
+        if !(gamma.is_finite() && gamma > 0.) {
+            return Err(format!(
+                "gamma has to be finite and above zero, not {}",
+                gamma
+            ));
+        }
+
+        let ((x0, y0), (x1, y1)) = raster.fit(area.0, area.1);
+        let (width, height) = (raster.width, raster.height);
+
+        let samples = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (col, row)))
+            .map(|(col, row)| {
+                // rasters run top down, the page bottom up
+                let x = x0 + (x1 - x0) * (col as f64 + 0.5) / width as f64;
+                let y = y1 - (y1 - y0) * (row as f64 + 0.5) / height as f64;
+                ((x, y), (1. - raster.get(col, row)).powf(gamma))
+            })
+            .filter(|&(_, weight)| weight > 0.)
+            .collect();
+        Ok(Self { samples })
+    }
+
+    /// One round of Lloyd's algorithm: each point moves to the weighted centroid of the samples
+    /// nearer to it than to any other point. Points with no weight in their cell stay put.
+    pub fn step(&self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        if points.is_empty() {
+            return Vec::new();
+        }
+
+        let nearest = Nearest::new(points);
+        let mut sums = vec![(0., 0., 0.); points.len()];
+        let mut last = nearest.start();
+        for &((x, y), weight) in &self.samples {
+            last = nearest.find(last, (x, y));
+            let sum = &mut sums[last];
+            sum.0 += x * weight;
+            sum.1 += y * weight;
+            sum.2 += weight;
+        }
+
+        points
+            .iter()
+            .zip(sums)
+            .map(|(&point, (x, y, weight))| {
+                if weight > 0. {
+                    (x / weight, y / weight)
+                } else {
+                    point
+                }
+            })
+            .collect()
+    }
+
+    /// `iterations` rounds of [`step`](Self::step).
+    pub fn relax(&self, points: Vec<(f64, f64)>, iterations: usize) -> Vec<(f64, f64)> {
+        (0..iterations).fold(points, |points, _| self.step(&points))
+    }
+}
+
+/// How to draw each stipple.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mark {
+    /// A small circle, `size` across.
+    Circle,
+    /// A horizontal stroke, `size` long. Much quicker to plot than circles.
+    Dash,
+}
+
+impl FromStr for Mark {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "circle" => Ok(Mark::Circle),
+            "dash" => Ok(Mark::Dash),
+            _ => Err(format!("unknown mark {:?} (expected circle or dash)", s)),
+        }
+    }
+}
+
+impl Mark {
+    /// The stroke for a stipple at `centre`.
+    pub fn draw(self, centre: (f64, f64), size: f64) -> Polyline {
+        match self {
+            // enough sides to look round at the sizes pens can manage
+            Mark::Circle => circle(centre, size / 2., 8),
+            Mark::Dash => vec![
+                (centre.0 - size / 2., centre.1),
+                (centre.0 + size / 2., centre.1),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const AREA: Area = ((0., 0.), (10., 10.));
+
+    #[test]
+    fn relaxes_towards_even_spacing() {
+        // bunched up in one corner
+        let points = (0..16)
+            .map(|i| {
+                (
+                    1. + 0.3 * (i % 4) as f64 + 0.01 * i as f64,
+                    1. + 0.3 * (i / 4) as f64,
+                )
+            })
+            .collect::<Vec<_>>();
+        let relaxed = Relaxation::uniform(AREA, 40).relax(points, 30);
+
+        // every quarter of the square ends up with its share
+        for &(qx, qy) in &[(0., 0.), (5., 0.), (0., 5.), (5., 5.)] {
+            let count = relaxed
+                .iter()
+                .filter(|&&(x, y)| x >= qx && x < qx + 5. && y >= qy && y < qy + 5.)
+                .count();
+            assert_eq!(count, 4, "{:?}", relaxed);
+        }
+    }
+
+    #[test]
+    fn weighting_pulls_points_into_the_dark() {
+        // dark on the left, white on the right
+        let pixels = (0..400)
+            .map(|i| if i % 20 < 10 { 0. } else { 1. })
+            .collect();
+        let raster = Raster::new(20, 20, pixels);
+        let points = vec![(2., 5.), (4., 5.), (6., 5.), (8., 5.), (5., 2.), (5., 8.)];
+        let relaxed = Relaxation::weighted(&raster, AREA, 1.)
+            .unwrap()
+            .relax(points, 20);
+        // the cells that include some dark come left; the rest have nothing to pull them
+        assert!(relaxed.iter().filter(|&&(x, _)| x < 5.).count() >= 4);
+        assert!(relaxed
+            .iter()
+            .all(|&(x, y)| (0. ..=10.).contains(&x) && (0. ..=10.).contains(&y)));
+    }
+
+    #[test]
+    fn gamma_must_be_positive() {
+        let raster = Raster::new(2, 2, vec![0., 1., 0.5, 1.]);
+        for &gamma in &[0., -1., f64::NAN, f64::INFINITY] {
+            assert!(
+                Relaxation::weighted(&raster, AREA, gamma).is_err(),
+                "{}",
+                gamma
+            );
+        }
+    }
+
+    #[test]
+    fn nearest_walks_to_the_closest() {
+        let points = (0..20)
+            .map(|i| ((i * 7 % 20) as f64, (i * 13 % 20) as f64 + 0.01 * i as f64))
+            .collect::<Vec<_>>();
+        let nearest = Nearest::new(&points);
+        for &query in &[(0., 0.), (19., 19.), (10., 3.), (-5., 30.)] {
+            let expected = (0..points.len())
+                .min_by(|&a, &b| {
+                    let d = |i: usize| (points[i].0 - query.0).hypot(points[i].1 - query.1);
+                    d(a).partial_cmp(&d(b)).unwrap()
+                })
+                .unwrap();
+            for from in 0..points.len() {
+                assert_eq!(nearest.find(from, query), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn marks() {
+        let circle = Mark::Circle.draw((1., 1.), 2.);
+        assert_eq!(circle.first(), circle.last());
+        assert!(circle
+            .iter()
+            .all(|&(x, y)| ((x - 1.).hypot(y - 1.) - 1.).abs() < 1e-9));
+        assert_eq!(Mark::Dash.draw((1., 1.), 2.), &[(0., 1.), (2., 1.)]);
+    }
+}